@@ -2,11 +2,149 @@
 /// 2023-05-11 Dorian Prill
 /// A very simple library for evaluating NURBS (non-uniform rational b-spline)
 /// curves and surfaces in arbitrary dimension.
-/// Consists of only three functions, no structures or traits.:
+/// The free functions below evaluate basis functions and points directly,
+/// the structs in `nurbs` validate their inputs and build on them:
+///     find_span()
+///     bspline_basis_nonzero()
 ///     bspline_basis()
-///     rational_bspline_basis()
+///     rational_bspline_basis_curve()
+///     rational_bspline_basis_surface()
 ///     nurbs_curve_point()
+///     nurbs_surface_point()
+mod linalg;
 pub mod nurbs;
+mod vector;
+
+// Finds the index `s` of the knot span with knots[s] <= t < knots[s+1] by binary search.
+// The number of control points is implied by the knot vector as knots.len() - degree - 1,
+// so the valid parameter domain is [knots[degree], knots[knots.len() - degree - 1]].
+// Parameters before the domain map to the first span and parameters at or after its end
+// map to the last non-empty span, which makes the curve closed at its end.
+pub fn find_span(degree: usize, knots: &[f64], t: f64) -> usize {
+    // index of the last control point
+    let n = knots.len() - degree - 2;
+    if t >= knots[n + 1] {
+        let mut span = n;
+        while span > degree && knots[span] >= knots[span + 1] {
+            span -= 1;
+        }
+        return span;
+    }
+    let t = t.max(knots[degree]);
+    let mut low = degree;
+    let mut high = n + 1;
+    let mut mid = (low + high) / 2;
+    while t < knots[mid] || t >= knots[mid + 1] {
+        if t < knots[mid] {
+            high = mid;
+        } else {
+            low = mid;
+        }
+        mid = (low + high) / 2;
+    }
+    mid
+}
+
+// Calculates all `degree + 1` B-Spline basis functions that are nonzero on the knot
+// span `span` (see find_span()) in one pass, evaluated at `t`.
+// The returned value at index j belongs to the basis function Bspan-degree+j,degree.
+// Instead of the recursion in bspline_basis() this builds the triangular table of
+// lower degree functions column by column, reusing the shared terms.
+pub fn bspline_basis_nonzero(span: usize, degree: usize, knots: &[f64], t: f64) -> Vec<f64> {
+    let mut basis = vec![0.0; degree + 1];
+    let mut left = vec![0.0; degree + 1];
+    let mut right = vec![0.0; degree + 1];
+    basis[0] = 1.0;
+    for j in 1..=degree {
+        left[j] = t - knots[span + 1 - j];
+        right[j] = knots[span + j] - t;
+        let mut saved = 0.0;
+        for r in 0..j {
+            let temp = basis[r] / (right[r + 1] + left[j - r]);
+            basis[r] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        basis[j] = saved;
+    }
+    basis
+}
+
+// Calculates all `degree + 1` nonzero B-Spline basis functions on the knot span `span`
+// together with their derivatives with respect to `t` up to `order`, evaluated at `t`.
+// The result is indexed as ders[k][j], the k-th derivative of Bspan-degree+j,degree,
+// so ders[0] equals bspline_basis_nonzero(). Derivatives above `degree` are zero.
+// The derivatives follow from differentiating the recursion,
+//  B'i,k(t) = k / (ti+k - ti) * Bi,k-1(t) - k / (ti+k+1 - ti+1) * Bi+1,k-1(t)
+// applied repeatedly to the triangular table of lower degree functions.
+pub fn bspline_basis_derivatives(
+    span: usize,
+    degree: usize,
+    knots: &[f64],
+    t: f64,
+    order: usize,
+) -> Vec<Vec<f64>> {
+    // ndu holds the basis functions in the upper and the knot differences
+    // in the lower triangle
+    let mut ndu = vec![vec![0.0; degree + 1]; degree + 1];
+    let mut left = vec![0.0; degree + 1];
+    let mut right = vec![0.0; degree + 1];
+    ndu[0][0] = 1.0;
+    for j in 1..=degree {
+        left[j] = t - knots[span + 1 - j];
+        right[j] = knots[span + j] - t;
+        let mut saved = 0.0;
+        for r in 0..j {
+            ndu[j][r] = right[r + 1] + left[j - r];
+            let temp = ndu[r][j - 1] / ndu[j][r];
+            ndu[r][j] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        ndu[j][j] = saved;
+    }
+
+    let mut ders = vec![vec![0.0; degree + 1]; order + 1];
+    for j in 0..=degree {
+        ders[0][j] = ndu[j][degree];
+    }
+    // two alternating rows of coefficients of the derivative
+    let mut a = vec![vec![0.0; degree + 1]; 2];
+    for r in 0..=degree {
+        let (mut s1, mut s2) = (0, 1);
+        a[0][0] = 1.0;
+        for k in 1..=order.min(degree) {
+            let mut d = 0.0;
+            let rk = r as isize - k as isize;
+            let pk = degree - k;
+            if r >= k {
+                let rk = rk as usize;
+                a[s2][0] = a[s1][0] / ndu[pk + 1][rk];
+                d = a[s2][0] * ndu[rk][pk];
+            }
+            let j1 = if rk >= -1 { 1 } else { (-rk) as usize };
+            let j2 = if r <= pk { k - 1 } else { degree - r };
+            for j in j1..=j2 {
+                let row = (rk + j as isize) as usize;
+                a[s2][j] = (a[s1][j] - a[s1][j - 1]) / ndu[pk + 1][row];
+                d += a[s2][j] * ndu[row][pk];
+            }
+            if r <= pk {
+                a[s2][k] = -a[s1][k - 1] / ndu[pk + 1][r];
+                d += a[s2][k] * ndu[r][pk];
+            }
+            ders[k][r] = d;
+            std::mem::swap(&mut s1, &mut s2);
+        }
+    }
+    // multiply through by the factors degree! / (degree - k)!
+    let mut factor = degree as f64;
+    for (k, row) in ders.iter_mut().enumerate().take(degree + 1).skip(1) {
+        for d in row.iter_mut() {
+            *d *= factor;
+        }
+        factor *= (degree - k) as f64;
+    }
+    ders
+}
 
 // Calculates the B-Spline Basis Function of `degree` at index `i`
 // over the non-uniform `knots`, evaluated at `t`.
@@ -15,31 +153,59 @@ pub mod nurbs;
 //              0 otherwise
 //  allowing for the recursive definition of a degree k B-Spline as
 //  Bi,k(t) := (t - ti) / (ti+k - ti) * Bi,k-1(t) + (ti+k+1 - t) / (ti+k+1 - ti+1) * Bi+1,k-1(t)
+// The last non-empty knot interval is also closed on the right, so that the basis
+// functions of a clamped knot vector still sum to one at its last knot.
+// Outside of the support [ti, ti+k+1] the function returns zero without recursing.
+// The recursion is evaluated bottom up in a table of the degree + 1 degree zero
+// functions that Bi,k depends on, so one call costs O(degree^2). Only at t = ti+k+1
+// the knots are also searched backwards for the last non-empty interval.
+// Use bspline_basis_nonzero() when all basis functions of a span are needed.
 pub fn bspline_basis(i: usize, degree: usize, knots: &[f64], t: f64) -> f64 {
-    if degree == 0 {
-        if (t >= knots[i] && t < knots[i + 1]) || (i + 1 == knots.len() - 1 && t == knots[i + 1]) {
-            1.0
-        } else {
-            0.0
-        }
+    // Bi,k is zero outside of its support [ti, ti+k+1]
+    if t < knots[i] || t > knots[i + degree + 1] {
+        return 0.0;
+    }
+    // the last non-empty interval can only be closed at the end of the support
+    let last = if t == knots[i + degree + 1] {
+        (0..knots.len() - 1)
+            .rev()
+            .find(|&j| knots[j] < knots[j + 1])
     } else {
-        let a = if (knots[i + degree] - knots[i]) == 0.0 {
-            0.0
-        } else {
-            (t - knots[i]) / (knots[i + degree] - knots[i]) * bspline_basis(i, degree - 1, knots, t)
-        };
-        let b = if (knots[i + degree + 1] - knots[i + 1]) == 0.0 {
-            0.0
-        } else {
-            (knots[i + degree + 1] - t) / (knots[i + degree + 1] - knots[i + 1])
-                * bspline_basis(i + 1, degree - 1, knots, t)
-        };
-        a + b
+        None
+    };
+    let mut table: Vec<f64> = (i..=i + degree)
+        .map(|j| {
+            if (t >= knots[j] && t < knots[j + 1]) || (Some(j) == last && t == knots[j + 1]) {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    for k in 1..=degree {
+        for j in 0..=degree - k {
+            let l = i + j;
+            let a = if (knots[l + k] - knots[l]) == 0.0 {
+                0.0
+            } else {
+                (t - knots[l]) / (knots[l + k] - knots[l]) * table[j]
+            };
+            let b = if (knots[l + k + 1] - knots[l + 1]) == 0.0 {
+                0.0
+            } else {
+                (knots[l + k + 1] - t) / (knots[l + k + 1] - knots[l + 1]) * table[j + 1]
+            };
+            table[j] = a + b;
+        }
     }
+    table[0]
 }
 
 // Calculates the rational (weighted) B-Spline Basis Function of `degree`
 // at index `i` over the `knots`, evaluated at `t`.
+// Only the basis functions of the knot span containing `t` contribute to the
+// denominator, so they are computed once with bspline_basis_nonzero().
+// Returns 0 for `t` outside of the domain of the knot vector.
 pub fn rational_bspline_basis_curve(
     i: usize,
     degree: usize,
@@ -47,19 +213,31 @@ pub fn rational_bspline_basis_curve(
     weights: &[f64],
     t: f64,
 ) -> f64 {
-    let numerator = weights[i] * bspline_basis(i, degree, knots, t);
-    let denominator: f64 = (0..weights.len())
-        .map(|j| weights[j] * bspline_basis(j, degree, knots, t))
+    if t < knots[degree] || t > knots[knots.len() - degree - 1] {
+        return 0.0;
+    }
+    let span = find_span(degree, knots, t);
+    if i + degree < span || i > span {
+        return 0.0;
+    }
+    let basis = bspline_basis_nonzero(span, degree, knots, t);
+    let first = span - degree;
+    let denominator: f64 = basis
+        .iter()
+        .enumerate()
+        .map(|(j, b)| weights[first + j] * b)
         .sum();
-    if denominator.abs() < std::f64::EPSILON {
+    if denominator.abs() < f64::EPSILON {
         0.0
     } else {
-        numerator / denominator
+        weights[i] * basis[i - first] / denominator
     }
 }
 
 // Calculates the rational B-Spline basis for surfaces over the parameters `u` and `v`
 // weights are accordingly also two-dimensional
+// Returns 0 for `u` or `v` outside of the domain of their knot vectors.
+#[allow(clippy::too_many_arguments)]
 pub fn rational_bspline_basis_surface(
     i: usize,
     j: usize,
@@ -71,20 +249,32 @@ pub fn rational_bspline_basis_surface(
     u: f64,
     v: f64,
 ) -> f64 {
-    let numerator_u = weights[i][j] * bspline_basis(i, degree_u, knots_u, u);
-    let numerator_v = weights[i][j] * bspline_basis(j, degree_v, knots_v, v);
-    let denominator: f64 = (0..weights.len())
-        .flat_map(|i| (0..weights[0].len()).map(move |j| (i, j)))
-        .map(|(i, j)| {
-            weights[i][j]
-                * bspline_basis(i, degree_u, knots_u, u)
-                * bspline_basis(j, degree_v, knots_v, v)
-        })
-        .sum();
-    if denominator.abs() < std::f64::EPSILON {
+    if u < knots_u[degree_u]
+        || u > knots_u[knots_u.len() - degree_u - 1]
+        || v < knots_v[degree_v]
+        || v > knots_v[knots_v.len() - degree_v - 1]
+    {
+        return 0.0;
+    }
+    let span_u = find_span(degree_u, knots_u, u);
+    let span_v = find_span(degree_v, knots_v, v);
+    if i + degree_u < span_u || i > span_u || j + degree_v < span_v || j > span_v {
+        return 0.0;
+    }
+    let basis_u = bspline_basis_nonzero(span_u, degree_u, knots_u, u);
+    let basis_v = bspline_basis_nonzero(span_v, degree_v, knots_v, v);
+    let first_u = span_u - degree_u;
+    let first_v = span_v - degree_v;
+    let mut denominator = 0.0;
+    for (k, bu) in basis_u.iter().enumerate() {
+        for (l, bv) in basis_v.iter().enumerate() {
+            denominator += weights[first_u + k][first_v + l] * bu * bv;
+        }
+    }
+    if denominator.abs() < f64::EPSILON {
         0.0
     } else {
-        (numerator_u * numerator_v) / denominator
+        weights[i][j] * basis_u[i - first_u] * basis_v[j - first_v] / denominator
     }
 }
 
@@ -94,29 +284,95 @@ pub fn rational_bspline_basis_surface(
 // It also assumes that the knots slice has the correct length for the number
 // of control points and the degree of the curve.
 // If these assumptions aren't met, the function may panic or return incorrect results.
-pub fn nurbs_curve_point<'a>(
+// Only the degree + 1 control points of the knot span containing `t` are visited.
+pub fn nurbs_curve_point(
     t: f64,
-    control_points: &'a [Vec<f64>],
-    weights: &'a [f64],
-    knots: &'a [f64],
+    control_points: &[Vec<f64>],
+    weights: &[f64],
+    knots: &[f64],
     degree: usize,
 ) -> Vec<f64> {
     let n_dims = control_points[0].len();
     let mut point = vec![0.0; n_dims];
 
-    for i in 0..control_points.len() {
-        let basis = rational_bspline_basis_curve(i, degree, knots, weights, t);
-        for k in 0..n_dims {
-            point[k] += basis * control_points[i][k];
+    let span = find_span(degree, knots, t);
+    let basis = bspline_basis_nonzero(span, degree, knots, t);
+    let mut weight = 0.0;
+    for (j, b) in basis.iter().enumerate() {
+        let i = span - degree + j;
+        let wb = weights[i] * b;
+        weight += wb;
+        for (p, c) in point.iter_mut().zip(&control_points[i]) {
+            *p += wb * c;
         }
     }
+    if weight.abs() >= f64::EPSILON {
+        point.iter_mut().for_each(|p| *p /= weight);
+    }
 
     point
 }
 
+// Binomial coefficient n over k as used by the rational quotient rule
+pub(crate) fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+// Calculates a point on a NURBS curve and its derivatives with respect to `t` up to `order`.
+// The result is indexed as ders[k], the k-th derivative, so ders[0] is the point itself.
+// The derivatives of the weighted (homogeneous) curve A(t) = sum Bi(t) * wi * Pi and of
+// the weight function w(t) = sum Bi(t) * wi are exact, the derivatives of the
+// rational curve C(t) = A(t) / w(t) follow from the quotient rule
+//  C(k) = (A(k) - sum_{i=1..k} binomial(k, i) * w(i) * C(k-i)) / w
+// Same assumptions as for nurbs_curve_point()
+pub fn nurbs_curve_derivatives(
+    t: f64,
+    control_points: &[Vec<f64>],
+    weights: &[f64],
+    knots: &[f64],
+    degree: usize,
+    order: usize,
+) -> Vec<Vec<f64>> {
+    let n_dims = control_points[0].len();
+    let span = find_span(degree, knots, t);
+    let basis = bspline_basis_derivatives(span, degree, knots, t, order);
+
+    let mut a_ders = vec![vec![0.0; n_dims]; order + 1];
+    let mut w_ders = vec![0.0; order + 1];
+    for (k, row) in basis.iter().enumerate() {
+        for (j, b) in row.iter().enumerate() {
+            let i = span - degree + j;
+            let wb = weights[i] * b;
+            w_ders[k] += wb;
+            for (a, c) in a_ders[k].iter_mut().zip(&control_points[i]) {
+                *a += wb * c;
+            }
+        }
+    }
+
+    let mut ders: Vec<Vec<f64>> = Vec::with_capacity(order + 1);
+    for k in 0..=order {
+        let mut v = a_ders[k].clone();
+        for i in 1..=k {
+            let factor = binomial(k, i) * w_ders[i];
+            for (x, c) in v.iter_mut().zip(&ders[k - i]) {
+                *x -= factor * c;
+            }
+        }
+        if w_ders[0].abs() >= f64::EPSILON {
+            v.iter_mut().for_each(|x| *x /= w_ders[0]);
+        }
+        ders.push(v);
+    }
+    ders
+}
+
 // Calculates a point on a NURBS surface
 // Same assumptions as for nurbs_curve_point()
-pub fn nurbs_surface_point<'a>(
+// Only the (degree_u + 1) * (degree_v + 1) control points of the knot spans
+// containing `u` and `v` are visited.
+#[allow(clippy::too_many_arguments)]
+pub fn nurbs_surface_point(
     control_points: &[Vec<Vec<f64>>],
     weights: &[Vec<f64>],
     knots_u: &[f64],
@@ -126,23 +382,106 @@ pub fn nurbs_surface_point<'a>(
     u: f64,
     v: f64,
 ) -> Vec<f64> {
-    let n = control_points.len();
-    let m = control_points[0].len();
     let d = control_points[0][0].len();
     let mut result = vec![0.0; d];
-    for i in 0..n {
-        for j in 0..m {
-            let basis = rational_bspline_basis_surface(
-                i, j, degree_u, degree_v, knots_u, knots_v, weights, u, v,
-            );
-            for k in 0..d {
-                result[k] += basis * control_points[i][j][k];
+
+    let span_u = find_span(degree_u, knots_u, u);
+    let span_v = find_span(degree_v, knots_v, v);
+    let basis_u = bspline_basis_nonzero(span_u, degree_u, knots_u, u);
+    let basis_v = bspline_basis_nonzero(span_v, degree_v, knots_v, v);
+    let mut weight = 0.0;
+    for (k, bu) in basis_u.iter().enumerate() {
+        let i = span_u - degree_u + k;
+        for (l, bv) in basis_v.iter().enumerate() {
+            let j = span_v - degree_v + l;
+            let wb = weights[i][j] * bu * bv;
+            weight += wb;
+            for (r, c) in result.iter_mut().zip(&control_points[i][j]) {
+                *r += wb * c;
             }
         }
     }
+    if weight.abs() >= f64::EPSILON {
+        result.iter_mut().for_each(|r| *r /= weight);
+    }
     result
 }
 
+// Calculates a point on a NURBS surface and its partial derivatives up to a total `order`.
+// The result is indexed as ders[k][l], the derivative k times with respect to `u` and
+// l times with respect to `v` for k + l <= order, so ders[0][0] is the point itself.
+// As for curves the derivatives of the homogeneous surface are exact and the rational
+// ones follow from the quotient rule in both parameters (The NURBS Book, eq. 4.20).
+// Same assumptions as for nurbs_curve_point()
+#[allow(clippy::too_many_arguments)]
+pub fn nurbs_surface_derivatives(
+    control_points: &[Vec<Vec<f64>>],
+    weights: &[Vec<f64>],
+    knots_u: &[f64],
+    knots_v: &[f64],
+    degree_u: usize,
+    degree_v: usize,
+    u: f64,
+    v: f64,
+    order: usize,
+) -> Vec<Vec<Vec<f64>>> {
+    let d = control_points[0][0].len();
+    let span_u = find_span(degree_u, knots_u, u);
+    let span_v = find_span(degree_v, knots_v, v);
+    let basis_u = bspline_basis_derivatives(span_u, degree_u, knots_u, u, order);
+    let basis_v = bspline_basis_derivatives(span_v, degree_v, knots_v, v, order);
+
+    let mut a_ders = vec![vec![vec![0.0; d]; order + 1]; order + 1];
+    let mut w_ders = vec![vec![0.0; order + 1]; order + 1];
+    for k in 0..=order {
+        for l in 0..=order - k {
+            for (r, bu) in basis_u[k].iter().enumerate() {
+                let i = span_u - degree_u + r;
+                for (s, bv) in basis_v[l].iter().enumerate() {
+                    let j = span_v - degree_v + s;
+                    let wb = weights[i][j] * bu * bv;
+                    w_ders[k][l] += wb;
+                    for (a, c) in a_ders[k][l].iter_mut().zip(&control_points[i][j]) {
+                        *a += wb * c;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut ders: Vec<Vec<Vec<f64>>> = (0..=order)
+        .map(|k| vec![Vec::new(); order - k + 1])
+        .collect();
+    for k in 0..=order {
+        for l in 0..=order - k {
+            let mut v = a_ders[k][l].clone();
+            for j in 1..=l {
+                let factor = binomial(l, j) * w_ders[0][j];
+                for (x, c) in v.iter_mut().zip(&ders[k][l - j]) {
+                    *x -= factor * c;
+                }
+            }
+            for i in 1..=k {
+                let factor = binomial(k, i) * w_ders[i][0];
+                for (x, c) in v.iter_mut().zip(&ders[k - i][l]) {
+                    *x -= factor * c;
+                }
+                for j in 1..=l {
+                    let factor = binomial(k, i) * binomial(l, j) * w_ders[i][j];
+                    for (x, c) in v.iter_mut().zip(&ders[k - i][l - j]) {
+                        *x -= factor * c;
+                    }
+                }
+            }
+            if w_ders[0][0].abs() >= f64::EPSILON {
+                v.iter_mut().for_each(|x| *x /= w_ders[0][0]);
+            }
+            ders[k][l] = v;
+        }
+    }
+    ders
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,9 +495,9 @@ mod tests {
                 let result = bspline_basis(i, 0, &knots, t);
                 dbg!(i, t, result);
                 if t >= knots[i] && t < knots[i + 1] {
-                    assert!((result - 1.0).abs() < std::f64::EPSILON);
+                    assert!((result - 1.0).abs() < f64::EPSILON);
                 } else {
-                    assert!(result.abs() < std::f64::EPSILON);
+                    assert!(result.abs() < f64::EPSILON);
                 }
             }
         }
@@ -228,8 +567,8 @@ mod tests {
                 let result = rational_bspline_basis_curve(i, 1, &knots, &weights, t);
                 dbg!(i, t, result);
                 let mut total = 0.0;
-                for j in 0..weights.len() {
-                    total += weights[j] * bspline_basis(j, 1, &knots, t);
+                for (j, w) in weights.iter().enumerate() {
+                    total += w * bspline_basis(j, 1, &knots, t);
                 }
                 if total.abs() > 0.000001 {
                     let expected = (weights[i] * bspline_basis(i, 1, &knots, t)) / total;
@@ -240,4 +579,151 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_find_span() {
+        let knots = vec![0.0, 0.0, 0.0, 1.0, 2.0, 2.0, 3.0, 3.0, 3.0];
+        assert_eq!(find_span(2, &knots, 0.0), 2);
+        assert_eq!(find_span(2, &knots, 0.5), 2);
+        assert_eq!(find_span(2, &knots, 1.0), 3);
+        // repeated knots are skipped over
+        assert_eq!(find_span(2, &knots, 2.0), 5);
+        assert_eq!(find_span(2, &knots, 2.5), 5);
+        // the end of the domain belongs to the last non-empty span
+        assert_eq!(find_span(2, &knots, 3.0), 5);
+    }
+
+    #[test]
+    fn test_bspline_basis_nonzero() {
+        // the non-recursive table has to agree with the single basis functions
+        // and the nonzero functions of a span have to sum to one
+        let knots = vec![0.0, 0.0, 0.0, 0.0, 0.5, 1.0, 2.0, 2.0, 3.0, 3.0, 3.0, 3.0];
+        let degree = 3;
+        for t in 0..=300 {
+            let t = t as f64 / 100.0;
+            let span = find_span(degree, &knots, t);
+            let basis = bspline_basis_nonzero(span, degree, &knots, t);
+            assert!((basis.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            for (j, b) in basis.iter().enumerate() {
+                let expected = bspline_basis(span - degree + j, degree, &knots, t);
+                assert!((b - expected).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nurbs_curve_point_end() {
+        // a clamped curve has to start and end at its first and last control point
+        let knots = vec![0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0];
+        let weights = vec![1.0, 2.0, 0.5, 1.0];
+        let control_points = vec![
+            vec![0.0, 0.0],
+            vec![1.0, 2.0],
+            vec![3.0, 2.0],
+            vec![4.0, 0.0],
+        ];
+        let start = nurbs_curve_point(0.0, &control_points, &weights, &knots, 2);
+        let end = nurbs_curve_point(1.0, &control_points, &weights, &knots, 2);
+        assert_eq!(start, vec![0.0, 0.0]);
+        assert_eq!(end, vec![4.0, 0.0]);
+    }
+
+    #[test]
+    fn test_bspline_basis_derivatives() {
+        // compare against central differences of the basis functions away from the knots
+        let knots = vec![0.0, 0.0, 0.0, 0.0, 0.5, 1.0, 2.0, 2.0, 3.0, 3.0, 3.0, 3.0];
+        let degree = 3;
+        let h = 1e-5;
+        for &t in &[0.1, 0.3, 0.7, 1.4, 1.9, 2.2, 2.9] {
+            let span = find_span(degree, &knots, t);
+            let ders = bspline_basis_derivatives(span, degree, &knots, t, 4);
+            assert_eq!(ders.len(), 5);
+            assert_eq!(ders[0], bspline_basis_nonzero(span, degree, &knots, t));
+            let minus = bspline_basis_nonzero(span, degree, &knots, t - h);
+            let plus = bspline_basis_nonzero(span, degree, &knots, t + h);
+            for j in 0..=degree {
+                let first = (plus[j] - minus[j]) / (2.0 * h);
+                let second = (plus[j] - 2.0 * ders[0][j] + minus[j]) / (h * h);
+                assert!((ders[1][j] - first).abs() < 1e-6);
+                assert!((ders[2][j] - second).abs() < 1e-3);
+                // derivatives above the degree vanish
+                assert_eq!(ders[4][j], 0.0);
+            }
+            // the basis functions sum to one, so their derivatives sum to zero
+            for der in &ders[1..] {
+                assert!(der.iter().sum::<f64>().abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nurbs_curve_derivatives() {
+        // quarter circle as a rational quadratic, its velocity is perpendicular to the
+        // radius and the derivatives have to match central differences
+        let knots = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let weights = vec![1.0, std::f64::consts::FRAC_1_SQRT_2, 1.0];
+        let control_points = vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 1.0]];
+        let h = 1e-5;
+        for &t in &[0.1, 0.5, 0.9] {
+            let ders = nurbs_curve_derivatives(t, &control_points, &weights, &knots, 2, 2);
+            let point = nurbs_curve_point(t, &control_points, &weights, &knots, 2);
+            let minus = nurbs_curve_point(t - h, &control_points, &weights, &knots, 2);
+            let plus = nurbs_curve_point(t + h, &control_points, &weights, &knots, 2);
+            assert_eq!(ders[0], point);
+            assert!((ders[0][0] * ders[1][0] + ders[0][1] * ders[1][1]).abs() < 1e-12);
+            for k in 0..2 {
+                let first = (plus[k] - minus[k]) / (2.0 * h);
+                let second = (plus[k] - 2.0 * point[k] + minus[k]) / (h * h);
+                assert!((ders[1][k] - first).abs() < 1e-6);
+                assert!((ders[2][k] - second).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nurbs_surface_derivatives() {
+        // compare against central differences of a rational biquadratic patch
+        let knots = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let weights = vec![
+            vec![1.0, 0.5, 1.0],
+            vec![2.0, 1.0, 0.7],
+            vec![1.0, 1.5, 1.0],
+        ];
+        let control_points: Vec<Vec<Vec<f64>>> = (0..3)
+            .map(|i| {
+                (0..3)
+                    .map(|j| vec![i as f64, j as f64, ((i + j) % 2) as f64])
+                    .collect()
+            })
+            .collect();
+        let point = |u: f64, v: f64| {
+            nurbs_surface_point(&control_points, &weights, &knots, &knots, 2, 2, u, v)
+        };
+        let h = 1e-4;
+        let (u, v) = (0.3, 0.6);
+        let ders =
+            nurbs_surface_derivatives(&control_points, &weights, &knots, &knots, 2, 2, u, v, 2);
+        assert_eq!(ders[0][0], point(u, v));
+        // weighted sum of points at offsets from (u, v)
+        let combine = |terms: &[(f64, f64, f64)]| -> Vec<f64> {
+            terms.iter().fold(vec![0.0; 3], |acc, &(c, du, dv)| {
+                let p = point(u + du, v + dv);
+                acc.iter().zip(&p).map(|(a, x)| a + c * x).collect()
+            })
+        };
+        let (a, b) = (0.5 / h, 1.0 / (h * h));
+        let su = combine(&[(a, h, 0.0), (-a, -h, 0.0)]);
+        let sv = combine(&[(a, 0.0, h), (-a, 0.0, -h)]);
+        let suu = combine(&[(b, h, 0.0), (-2.0 * b, 0.0, 0.0), (b, -h, 0.0)]);
+        let suv = combine(&[
+            (b / 4.0, h, h),
+            (-b / 4.0, h, -h),
+            (-b / 4.0, -h, h),
+            (b / 4.0, -h, -h),
+        ]);
+        assert!(vector::norm(&vector::sub(&ders[1][0], &su)) < 1e-6);
+        assert!(vector::norm(&vector::sub(&ders[0][1], &sv)) < 1e-6);
+        assert!(vector::norm(&vector::sub(&ders[2][0], &suu)) < 1e-4);
+        assert!(vector::norm(&vector::sub(&ders[1][1], &suv)) < 1e-4);
+    }
 }
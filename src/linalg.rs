@@ -0,0 +1,150 @@
+// linalg.rs
+// A small banded linear solver for the systems of curve and surface fitting.
+// Collocation matrices of B-Splines only have nonzero entries close to the diagonal,
+// so they are stored by rows covering just the band and solved in O(n * bandwidth^2).
+
+// A square matrix whose nonzero entries in row i lie in the columns i - lower to i + upper
+pub struct BandMatrix {
+    lower: usize,
+    upper: usize,
+    // every row as its first column and the entries from there on,
+    // rows grow to the right with the fill-in caused by row swaps
+    rows: Vec<(usize, Vec<f64>)>,
+}
+
+impl BandMatrix {
+    pub fn new(n: usize, lower: usize, upper: usize) -> Self {
+        let rows = (0..n)
+            .map(|i| {
+                let first = i.saturating_sub(lower);
+                let last = (i + upper).min(n - 1);
+                (first, vec![0.0; last + 1 - first])
+            })
+            .collect();
+        BandMatrix { lower, upper, rows }
+    }
+
+    // Builds the matrix from sparse rows given as their first column and consecutive entries
+    pub fn from_rows(rows: &[(usize, Vec<f64>)]) -> Self {
+        let n = rows.len();
+        let (mut lower, mut upper) = (0, 0);
+        for (i, (first, values)) in rows.iter().enumerate() {
+            lower = lower.max(i.saturating_sub(*first));
+            upper = upper.max((first + values.len()).saturating_sub(i + 1));
+        }
+        let mut matrix = BandMatrix::new(n, lower, upper);
+        for (i, (first, values)) in rows.iter().enumerate() {
+            for (k, &value) in values.iter().enumerate() {
+                matrix.add(i, first + k, value);
+            }
+        }
+        matrix
+    }
+
+    // Adds `value` to the entry (i, j), which must lie within the band
+    pub fn add(&mut self, i: usize, j: usize, value: f64) {
+        debug_assert!(j + self.lower >= i && j <= i + self.upper);
+        let (first, values) = &mut self.rows[i];
+        values[j - *first] += value;
+    }
+
+    // Solves A X = B for every column of B, given as one row of B per matrix row.
+    // Uses Gaussian elimination with partial pivoting within the band.
+    pub fn solve(mut self, mut rhs: Vec<Vec<f64>>) -> Result<Vec<Vec<f64>>, &'static str> {
+        let n = self.rows.len();
+        let scale = self
+            .rows
+            .iter()
+            .flat_map(|(_, values)| values.iter())
+            .fold(0.0_f64, |m, v| m.max(v.abs()));
+        for c in 0..n {
+            let last = (c + self.lower).min(n - 1);
+            let pivot = (c..=last)
+                .max_by(|&a, &b| self.get(a, c).abs().total_cmp(&self.get(b, c).abs()))
+                .unwrap_or(c);
+            if self.get(pivot, c).abs() <= 1e-14 * scale {
+                return Err("The linear system is singular");
+            }
+            self.rows.swap(c, pivot);
+            rhs.swap(c, pivot);
+            let (first_c, row_c) = self.rows[c].clone();
+            let diagonal = row_c[c - first_c];
+            for r in c + 1..=last {
+                let factor = self.get(r, c) / diagonal;
+                if factor == 0.0 {
+                    continue;
+                }
+                let (first_r, row_r) = &mut self.rows[r];
+                for (k, value) in row_c.iter().enumerate().skip(c - first_c) {
+                    let j = first_c + k - *first_r;
+                    if j >= row_r.len() {
+                        row_r.resize(j + 1, 0.0);
+                    }
+                    row_r[j] -= factor * value;
+                }
+                let (head, tail) = rhs.split_at_mut(r);
+                for (x, b) in tail[0].iter_mut().zip(&head[c]) {
+                    *x -= factor * b;
+                }
+            }
+        }
+        // back substitution on the upper triangle
+        for c in (0..n).rev() {
+            let (first, row) = &self.rows[c];
+            let (head, tail) = rhs.split_at_mut(c + 1);
+            let x = &mut head[c];
+            for (k, value) in row.iter().enumerate().skip(c + 1 - first) {
+                for (xi, bi) in x.iter_mut().zip(&tail[first + k - c - 1]) {
+                    *xi -= value * bi;
+                }
+            }
+            let diagonal = row[c - first];
+            x.iter_mut().for_each(|xi| *xi /= diagonal);
+        }
+        Ok(rhs)
+    }
+
+    fn get(&self, i: usize, j: usize) -> f64 {
+        let (first, values) = &self.rows[i];
+        if j < *first {
+            0.0
+        } else {
+            values.get(j - first).copied().unwrap_or(0.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BandMatrix;
+
+    #[test]
+    fn test_band_solve() {
+        // a tridiagonal system that needs a row swap in the first column
+        let rows = vec![
+            (0, vec![1.0, 2.0]),
+            (0, vec![3.0, 1.0, 1.0]),
+            (1, vec![1.0, 4.0, 1.0]),
+            (2, vec![2.0, 5.0]),
+        ];
+        let matrix = BandMatrix::from_rows(&rows);
+        let x = [1.0, -2.0, 3.0, 0.5];
+        let rhs: Vec<Vec<f64>> = rows
+            .iter()
+            .map(|(first, values)| {
+                let b: f64 = values
+                    .iter()
+                    .enumerate()
+                    .map(|(k, v)| v * x[first + k])
+                    .sum();
+                vec![b, 2.0 * b]
+            })
+            .collect();
+        let solution = matrix.solve(rhs).unwrap();
+        for (s, x) in solution.iter().zip(x) {
+            assert!((s[0] - x).abs() < 1e-12 && (s[1] - 2.0 * x).abs() < 1e-12);
+        }
+        let singular = BandMatrix::from_rows(&[(0, vec![1.0, 1.0]), (0, vec![2.0, 2.0])]);
+        assert!(singular.solve(vec![vec![1.0], vec![2.0]]).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+// nurbs/arc_length.rs
+// Arc length of NURBS curves and the inverse mapping from length to parameter
+use crate::nurbs::NURBSCurve;
+use crate::nurbs_curve_point;
+use crate::vector::norm;
+
+// nodes and weights of the 8 point Gauss-Legendre rule on [-1, 1]
+const GAUSS_NODES: [f64; 4] = [
+    0.183_434_642_495_649_8,
+    0.525_532_409_916_329,
+    0.796_666_477_413_626_7,
+    0.960_289_856_497_536_2,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.362_683_783_378_362,
+    0.313_706_645_877_887_3,
+    0.222_381_034_453_374_5,
+    0.101_228_536_290_376_3,
+];
+// tolerance of the adaptive quadrature and of the inverse mapping, relative to the
+// length of a knot span once it is longer than 1
+const TOLERANCE: f64 = 1e-10;
+const MAX_DEPTH: usize = 20;
+
+impl NURBSCurve {
+    // Length of the curve between the parameters `t0` <= `t1`.
+    // The speed |C'(t)| is integrated with adaptive Gauss-Legendre quadrature separately
+    // over each knot span, where it is smooth.
+    pub fn arc_length(&self, t0: f64, t1: f64) -> Result<f64, &'static str> {
+        let (start, end) = self.domain();
+        if t0 < start || t1 > end {
+            return Err("The parameter t is out of range of the knot values");
+        }
+        if t0 > t1 {
+            return Err("t0 must not be greater than t1");
+        }
+        let mut length = 0.0;
+        let mut a = t0;
+        for &b in self.breakpoints().iter().filter(|&&b| b > t0) {
+            let b = b.min(t1);
+            length += self.span_length(a, b);
+            if b >= t1 {
+                break;
+            }
+            a = b;
+        }
+        Ok(length)
+    }
+
+    // The parameter at which the arc length measured from the start of the curve equals `s`
+    pub fn param_at_length(&self, s: f64) -> Result<f64, &'static str> {
+        self.param_at_length_from(self.domain().0, s)
+    }
+
+    // Iterates over `count` points spaced equally in distance along the curve, from its
+    // start to its end. Each item is the parameter together with the point. The parameters
+    // are all found here, so any error is returned up front and the points are evaluated
+    // as the iterator advances.
+    pub fn equidistant_points(&self, count: usize) -> Result<EquidistantPoints<'_>, &'static str> {
+        if count < 2 {
+            return Err("At least two points are needed");
+        }
+        let (start, end) = self.domain();
+        let step = self.arc_length(start, end)? / (count - 1) as f64;
+        let mut params = Vec::with_capacity(count);
+        params.push(start);
+        for _ in 1..count - 1 {
+            // every parameter is found from its predecessor, so each step only integrates
+            // over the stretch of curve in between
+            let t = self.param_at_length_from(params[params.len() - 1], step)?;
+            params.push(t);
+        }
+        // land exactly on the end instead of accumulating the error of each step
+        params.push(end);
+        Ok(EquidistantPoints {
+            curve: self,
+            params: params.into_iter(),
+        })
+    }
+
+    // The parameter at which the arc length measured from `t0` equals `s`.
+    // Finds the knot span containing it from the span lengths and solves within it
+    // with Newton's method, falling back to bisection when a step leaves the bracket.
+    fn param_at_length_from(&self, t0: f64, s: f64) -> Result<f64, &'static str> {
+        let (start, end) = self.domain();
+        if t0 < start || t0 > end {
+            return Err("The parameter t is out of range of the knot values");
+        }
+        if s < 0.0 {
+            return Err("The arc length must be non-negative");
+        }
+        let mut remaining = s;
+        let mut a = t0;
+        for &b in self.breakpoints().iter().filter(|&&b| b > t0) {
+            let length = self.span_length(a, b);
+            if remaining > length {
+                remaining -= length;
+                a = b;
+                continue;
+            }
+            let (mut low, mut high) = (a, b);
+            let mut t = a + (b - a) * remaining / length.max(f64::EPSILON);
+            let tolerance = TOLERANCE * length.max(1.0);
+            for _ in 0..50 {
+                let residual = self.span_length(a, t) - remaining;
+                if residual.abs() < tolerance {
+                    break;
+                }
+                if residual > 0.0 {
+                    high = t;
+                } else {
+                    low = t;
+                }
+                let speed = self.speed(t);
+                let newton = t - residual / speed;
+                t = if speed > f64::EPSILON && newton > low && newton < high {
+                    newton
+                } else {
+                    0.5 * (low + high)
+                };
+            }
+            return Ok(t);
+        }
+        if remaining <= TOLERANCE * s.max(1.0) {
+            Ok(end)
+        } else {
+            Err("The arc length exceeds the length of the curve")
+        }
+    }
+
+    fn speed(&self, t: f64) -> f64 {
+        self.derivatives(t, 1)
+            .map(|ders| norm(&ders[1]))
+            .unwrap_or(0.0)
+    }
+
+    // Integrates the speed over [a, b], which must lie within one knot span
+    fn span_length(&self, a: f64, b: f64) -> f64 {
+        let whole = self.gauss_legendre(a, b);
+        self.adaptive_length(a, b, whole, TOLERANCE * whole.max(1.0), 0)
+    }
+
+    fn adaptive_length(&self, a: f64, b: f64, whole: f64, tolerance: f64, depth: usize) -> f64 {
+        let mid = 0.5 * (a + b);
+        let left = self.gauss_legendre(a, mid);
+        let right = self.gauss_legendre(mid, b);
+        if depth >= MAX_DEPTH || (left + right - whole).abs() < tolerance {
+            left + right
+        } else {
+            self.adaptive_length(a, mid, left, tolerance, depth + 1)
+                + self.adaptive_length(mid, b, right, tolerance, depth + 1)
+        }
+    }
+
+    fn gauss_legendre(&self, a: f64, b: f64) -> f64 {
+        let half = 0.5 * (b - a);
+        let mid = 0.5 * (a + b);
+        GAUSS_NODES
+            .iter()
+            .zip(&GAUSS_WEIGHTS)
+            .map(|(x, w)| w * (self.speed(mid - half * x) + self.speed(mid + half * x)))
+            .sum::<f64>()
+            * half
+    }
+}
+
+// Iterator over points spaced equally in arc length, see NURBSCurve::equidistant_points()
+pub struct EquidistantPoints<'a> {
+    curve: &'a NURBSCurve,
+    params: std::vec::IntoIter<f64>,
+}
+
+impl Iterator for EquidistantPoints<'_> {
+    type Item = (f64, Vec<f64>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let t = self.params.next()?;
+        // the parameters lie within the domain, so the point is evaluated without checks
+        let curve = self.curve;
+        let point = nurbs_curve_point(
+            t,
+            curve.control_points(),
+            curve.weights(),
+            curve.knots(),
+            curve.degree(),
+        );
+        Some((t, point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::NURBSCurve;
+    use crate::vector::{norm, sub};
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+    // unit circle made of four rational quadratic quarters
+    fn circle() -> NURBSCurve {
+        let w = FRAC_1_SQRT_2;
+        NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0, 4.0],
+            vec![1.0, w, 1.0, w, 1.0, w, 1.0, w, 1.0],
+            vec![
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+                vec![0.0, 1.0],
+                vec![-1.0, 1.0],
+                vec![-1.0, 0.0],
+                vec![-1.0, -1.0],
+                vec![0.0, -1.0],
+                vec![1.0, -1.0],
+                vec![1.0, 0.0],
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_arc_length() {
+        let circle = circle();
+        assert!((circle.arc_length(0.0, 4.0).unwrap() - 2.0 * PI).abs() < 1e-9);
+        assert!((circle.arc_length(1.0, 2.0).unwrap() - PI / 2.0).abs() < 1e-9);
+        assert!((circle.arc_length(0.5, 0.5).unwrap()).abs() < 1e-12);
+        assert!(circle.arc_length(2.0, 1.0).is_err());
+        assert!(circle.arc_length(0.0, 5.0).is_err());
+    }
+
+    #[test]
+    fn test_large_coordinates() {
+        // a circle of radius 1e6, the tolerances scale with the length of the spans
+        let circle = circle();
+        let points = circle
+            .control_points()
+            .iter()
+            .map(|p| p.iter().map(|x| x * 1e6).collect())
+            .collect();
+        let large = NURBSCurve::new(
+            2,
+            circle.knots().to_vec(),
+            circle.weights().to_vec(),
+            points,
+        )
+        .unwrap();
+        let length = large.arc_length(0.0, 4.0).unwrap();
+        assert!((length / (2e6 * PI) - 1.0).abs() < 1e-12);
+        assert!((large.param_at_length(0.5 * length).unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(large.param_at_length(length).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_param_at_length() {
+        let circle = circle();
+        // the quarters join at the knots
+        assert!((circle.param_at_length(PI).unwrap() - 2.0).abs() < 1e-8);
+        for &s in &[0.3, 1.0, 2.5, 4.0, 6.0] {
+            let t = circle.param_at_length(s).unwrap();
+            assert!((circle.arc_length(0.0, t).unwrap() - s).abs() < 1e-8);
+        }
+        assert!(circle.param_at_length(7.0).is_err());
+    }
+
+    #[test]
+    fn test_equidistant_points() {
+        // the rational parameterization is not uniform in length, the points have to be
+        let circle = circle();
+        let points: Vec<(f64, Vec<f64>)> = circle.equidistant_points(13).unwrap().collect();
+        assert_eq!(points.len(), 13);
+        let chord = 2.0 * (PI / 12.0).sin();
+        for pair in points.windows(2) {
+            assert!((norm(&sub(&pair[1].1, &pair[0].1)) - chord).abs() < 1e-8);
+        }
+        assert_eq!(points[12].0, 4.0);
+    }
+}
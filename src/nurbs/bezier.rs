@@ -0,0 +1,227 @@
+// nurbs/bezier.rs
+// Decomposition of NURBS curves and surfaces into rational Bézier segments and patches
+use crate::nurbs::knots::{breakpoints, decompose_rows};
+use crate::nurbs::nurbs_surface::transpose;
+use crate::nurbs::{NURBSCurve, NURBSSurface, Rows};
+use crate::vector::{cartesian, homogeneous};
+
+// A rational Bézier segment of a curve, covering the parameters t0..=t1 of the curve.
+// Its degree is the number of control points minus one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BezierSegment {
+    pub t0: f64,
+    pub t1: f64,
+    pub control_points: Vec<Vec<f64>>,
+    pub weights: Vec<f64>,
+}
+
+// A rational Bézier patch of a surface, covering the parameters u0..=u1 and v0..=v1 of
+// the surface. Control points and weights are indexed [i][j] like those of NURBSSurface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BezierPatch {
+    pub u0: f64,
+    pub u1: f64,
+    pub v0: f64,
+    pub v1: f64,
+    pub control_points: Vec<Vec<Vec<f64>>>,
+    pub weights: Vec<Vec<f64>>,
+}
+
+// Evaluates a Bézier curve given by homogeneous control points at the local parameter `s`
+// in [0, 1] with de Casteljau's algorithm
+fn de_casteljau(points_w: &[Vec<f64>], s: f64) -> Vec<f64> {
+    let mut points = points_w.to_vec();
+    for k in 1..points.len() {
+        for i in 0..points.len() - k {
+            points[i] = points[i]
+                .iter()
+                .zip(&points[i + 1])
+                .map(|(a, b)| (1.0 - s) * a + s * b)
+                .collect();
+        }
+    }
+    points.swap_remove(0)
+}
+
+// Projects a homogeneous point back to cartesian coordinates
+fn project(point_w: Vec<f64>) -> Vec<f64> {
+    cartesian(&[point_w]).0.swap_remove(0)
+}
+
+impl BezierSegment {
+    pub fn degree(&self) -> usize {
+        self.control_points.len() - 1
+    }
+
+    // Evaluates the segment at the local parameter `s` in [0, 1],
+    // which corresponds to t0 + s * (t1 - t0) on the original curve
+    pub fn eval(&self, s: f64) -> Vec<f64> {
+        project(de_casteljau(
+            &homogeneous(&self.control_points, &self.weights),
+            s,
+        ))
+    }
+}
+
+impl BezierPatch {
+    pub fn degree_u(&self) -> usize {
+        self.control_points.len() - 1
+    }
+
+    pub fn degree_v(&self) -> usize {
+        self.control_points[0].len() - 1
+    }
+
+    // Evaluates the patch at the local parameters `s` and `r` in [0, 1], which correspond
+    // to u0 + s * (u1 - u0) and v0 + r * (v1 - v0) on the original surface
+    pub fn eval(&self, s: f64, r: f64) -> Vec<f64> {
+        let column: Vec<Vec<f64>> = self
+            .control_points
+            .iter()
+            .zip(&self.weights)
+            .map(|(row, weights)| de_casteljau(&homogeneous(row, weights), r))
+            .collect();
+        project(de_casteljau(&column, s))
+    }
+}
+
+// Splits every row into Bézier segments, returning the breakpoints and the segments
+// indexed as [row][segment][point]
+pub(crate) fn bezier_segments(
+    degree: usize,
+    knots: &[f64],
+    rows: &[Vec<Vec<f64>>],
+) -> (Vec<f64>, Vec<Rows>) {
+    let (knots, rows) = decompose_rows(degree, knots, rows);
+    let breaks = breakpoints(degree, &knots);
+    let segments = rows
+        .iter()
+        .map(|row| {
+            (0..breaks.len() - 1)
+                .map(|k| row[k * degree..=(k + 1) * degree].to_vec())
+                .collect()
+        })
+        .collect();
+    (breaks, segments)
+}
+
+impl NURBSCurve {
+    // Converts the curve into its rational Bézier segments, one per non-empty knot span
+    pub fn to_bezier(&self) -> Vec<BezierSegment> {
+        let (breaks, segments) =
+            bezier_segments(self.degree(), self.knots(), &[self.homogeneous_points()]);
+        segments[0]
+            .iter()
+            .zip(breaks.windows(2))
+            .map(|(points_w, span)| {
+                let (control_points, weights) = cartesian(points_w);
+                BezierSegment {
+                    t0: span[0],
+                    t1: span[1],
+                    control_points,
+                    weights,
+                }
+            })
+            .collect()
+    }
+}
+
+impl NURBSSurface {
+    // Converts the surface into its rational Bézier patches, indexed as [span_u][span_v]
+    pub fn to_bezier(&self) -> Vec<Vec<BezierPatch>> {
+        let (p, q) = (self.degree_u(), self.degree_v());
+        // decompose along u first, which gives the patch rows along v as segments
+        let (knots_u, rows) = decompose_rows(p, self.knots_u(), &self.rows_u());
+        let (breaks_v, segments) = bezier_segments(q, self.knots_v(), &transpose(&rows));
+        let breaks_u = breakpoints(p, &knots_u);
+        (0..breaks_u.len() - 1)
+            .map(|a| {
+                (0..breaks_v.len() - 1)
+                    .map(|b| {
+                        let (control_points, weights) = segments[a * p..=(a + 1) * p]
+                            .iter()
+                            .map(|row| cartesian(&row[b]))
+                            .unzip();
+                        BezierPatch {
+                            u0: breaks_u[a],
+                            u1: breaks_u[a + 1],
+                            v0: breaks_v[b],
+                            v1: breaks_v[b + 1],
+                            control_points,
+                            weights,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{norm, sub};
+
+    #[test]
+    fn test_curve_to_bezier() {
+        let curve = NURBSCurve::new(
+            3,
+            vec![0.0, 0.0, 0.0, 0.0, 0.4, 0.7, 0.7, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0, 0.5, 2.0, 1.0, 1.0, 1.5, 1.0],
+            vec![
+                vec![0.0, 0.0],
+                vec![1.0, 2.0],
+                vec![2.0, 2.0],
+                vec![3.0, 0.0],
+                vec![4.0, 1.0],
+                vec![5.0, 1.0],
+                vec![6.0, 0.0],
+            ],
+        )
+        .unwrap();
+        let segments = curve.to_bezier();
+        assert_eq!(segments.len(), 3);
+        assert_eq!((segments[1].t0, segments[1].t1), (0.4, 0.7));
+        for segment in &segments {
+            assert_eq!(segment.degree(), 3);
+            for i in 0..=10 {
+                let s = i as f64 / 10.0;
+                let t = segment.t0 + s * (segment.t1 - segment.t0);
+                let point = curve.eval(t).unwrap();
+                assert!(norm(&sub(&segment.eval(s), &point)) < 1e-12);
+            }
+        }
+        // neighbouring segments share their end points
+        assert_eq!(segments[0].control_points[3], segments[1].control_points[0]);
+    }
+
+    #[test]
+    fn test_surface_to_bezier() {
+        let knots_u = vec![0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0];
+        let knots_v = vec![0.0, 0.0, 0.3, 0.6, 1.0, 1.0];
+        let weights = vec![vec![1.0, 2.0, 1.0, 0.5]; 4];
+        let control_points = (0..4)
+            .map(|i| {
+                (0..4)
+                    .map(|j| vec![i as f64, j as f64, ((i + 2 * j) % 3) as f64])
+                    .collect()
+            })
+            .collect();
+        let surface = NURBSSurface::new(2, 1, knots_u, knots_v, weights, control_points).unwrap();
+        let patches = surface.to_bezier();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].len(), 3);
+        for patch in patches.iter().flatten() {
+            assert_eq!((patch.degree_u(), patch.degree_v()), (2, 1));
+            for i in 0..=5 {
+                for j in 0..=5 {
+                    let (s, r) = (i as f64 / 5.0, j as f64 / 5.0);
+                    let u = patch.u0 + s * (patch.u1 - patch.u0);
+                    let v = patch.v0 + r * (patch.v1 - patch.v0);
+                    let point = surface.eval(u, v).unwrap();
+                    assert!(norm(&sub(&patch.eval(s, r), &point)) < 1e-12);
+                }
+            }
+        }
+    }
+}
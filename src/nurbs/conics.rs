@@ -0,0 +1,229 @@
+// nurbs/conics.rs
+// Exact circles, ellipses and general conic arcs as quadratic rational curves
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::nurbs::NURBSCurve;
+use crate::vector::{add, dot, norm, normalize, scale, sub};
+
+impl NURBSCurve {
+    // The full circle around `center` in the plane spanned by the perpendicular
+    // `x_axis` and `y_axis`, starting at center + radius * x_axis and running towards y_axis
+    pub fn circle(
+        center: &[f64],
+        x_axis: &[f64],
+        y_axis: &[f64],
+        radius: f64,
+    ) -> Result<NURBSCurve, &'static str> {
+        NURBSCurve::arc(center, x_axis, y_axis, radius, 0.0, 2.0 * PI)
+    }
+
+    // The circular arc from `start_angle` to `end_angle` (in radians, measured from x_axis
+    // towards y_axis) around `center` in the plane of the perpendicular `x_axis` and `y_axis`.
+    // The axes need not be unit vectors. An end angle below the start angle wraps around.
+    pub fn arc(
+        center: &[f64],
+        x_axis: &[f64],
+        y_axis: &[f64],
+        radius: f64,
+        start_angle: f64,
+        end_angle: f64,
+    ) -> Result<NURBSCurve, &'static str> {
+        if radius <= 0.0 {
+            return Err("The radius must be positive");
+        }
+        let x_axis = normalize(x_axis).ok_or("The axes must not vanish")?;
+        let y_axis = normalize(y_axis).ok_or("The axes must not vanish")?;
+        if dot(&x_axis, &y_axis).abs() > 1e-10 {
+            return Err("The axes must be perpendicular");
+        }
+        NURBSCurve::elliptical_arc(
+            center,
+            &scale(&x_axis, radius),
+            &scale(&y_axis, radius),
+            start_angle,
+            end_angle,
+        )
+    }
+
+    // The full ellipse center + cos(a) * major_axis + sin(a) * minor_axis, the lengths of
+    // the axes are the semi-axes
+    pub fn ellipse(
+        center: &[f64],
+        major_axis: &[f64],
+        minor_axis: &[f64],
+    ) -> Result<NURBSCurve, &'static str> {
+        NURBSCurve::elliptical_arc(center, major_axis, minor_axis, 0.0, 2.0 * PI)
+    }
+
+    // The arc of center + cos(a) * major_axis + sin(a) * minor_axis for a from `start_angle`
+    // to `end_angle`. The arc is split into at most four equal pieces of up to 90 degrees,
+    // each a quadratic rational Bézier segment with the weights 1, cos(da / 2), 1 joined
+    // by double knots (The NURBS Book, A7.1). Any two linearly independent axes work,
+    // since the construction is invariant under affine maps.
+    pub fn elliptical_arc(
+        center: &[f64],
+        major_axis: &[f64],
+        minor_axis: &[f64],
+        start_angle: f64,
+        mut end_angle: f64,
+    ) -> Result<NURBSCurve, &'static str> {
+        if center.len() < 2 || major_axis.len() != center.len() || minor_axis.len() != center.len()
+        {
+            return Err("The center and the axes must have the same dimension of at least 2");
+        }
+        let (a2, b2, ab) = (
+            dot(major_axis, major_axis),
+            dot(minor_axis, minor_axis),
+            dot(major_axis, minor_axis),
+        );
+        if a2 * b2 - ab * ab <= 1e-20 * a2 * b2 || a2 * b2 == 0.0 {
+            return Err("The axes must be linearly independent");
+        }
+        if end_angle < start_angle {
+            end_angle += 2.0 * PI;
+        }
+        let sweep = end_angle - start_angle;
+        if sweep <= 0.0 || sweep > 2.0 * PI + 1e-12 {
+            return Err("The arc must span an angle between 0 and 2 pi");
+        }
+
+        let arcs = ((sweep / FRAC_PI_2 - 1e-12).ceil() as usize).clamp(1, 4);
+        let delta = sweep / arcs as f64;
+        let w1 = (0.5 * delta).cos();
+        let on_ellipse = |angle: f64, factor: f64| {
+            add(
+                center,
+                &add(
+                    &scale(major_axis, factor * angle.cos()),
+                    &scale(minor_axis, factor * angle.sin()),
+                ),
+            )
+        };
+
+        let mut control_points = vec![on_ellipse(start_angle, 1.0)];
+        let mut weights = vec![1.0];
+        let mut knots = vec![0.0; 3];
+        for i in 1..=arcs {
+            let angle = start_angle + delta * i as f64;
+            // the tangents at both ends meet at the middle control point
+            control_points.push(on_ellipse(angle - 0.5 * delta, 1.0 / w1));
+            control_points.push(on_ellipse(angle, 1.0));
+            weights.extend([w1, 1.0]);
+            if i < arcs {
+                knots.extend([i as f64 / arcs as f64; 2]);
+            }
+        }
+        knots.extend([1.0; 3]);
+        NURBSCurve::new(2, knots, weights, control_points)
+    }
+
+    // The general conic arc from `start` to `end` with the tangents at both ends meeting in
+    // `apex`, a quadratic rational Bézier curve with the weights 1, `weight`, 1.
+    // A weight below 1 gives an elliptical, exactly 1 a parabolic and above 1 a hyperbolic arc.
+    pub fn conic_arc(
+        start: &[f64],
+        apex: &[f64],
+        end: &[f64],
+        weight: f64,
+    ) -> Result<NURBSCurve, &'static str> {
+        if weight <= 0.0 {
+            return Err("The weight must be positive");
+        }
+        if apex.len() != start.len() || end.len() != start.len() {
+            return Err("All control points must be of the same dimension");
+        }
+        if norm(&sub(start, apex)) == 0.0 || norm(&sub(end, apex)) == 0.0 {
+            return Err("The apex must differ from the end points");
+        }
+        NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![1.0, weight, 1.0],
+            vec![start.to_vec(), apex.to_vec(), end.to_vec()],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::NURBSCurve;
+    use crate::vector::{norm, sub};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_circle_and_arcs() {
+        let center = [1.0, 2.0, 3.0];
+        let (x_axis, y_axis) = ([0.0, 2.0, 0.0], [0.0, 0.0, 1.0]);
+        let circle = NURBSCurve::circle(&center, &x_axis, &y_axis, 2.0).unwrap();
+        assert_eq!(circle.control_points().len(), 9);
+        assert_eq!(
+            circle.knots(),
+            &[0.0, 0.0, 0.0, 0.25, 0.25, 0.5, 0.5, 0.75, 0.75, 1.0, 1.0, 1.0]
+        );
+        assert!(circle.is_closed());
+        for i in 0..=20 {
+            let point = circle.eval(i as f64 / 20.0).unwrap();
+            assert!((norm(&sub(&point, &center)) - 2.0).abs() < 1e-12);
+            assert!((point[0] - 1.0).abs() < 1e-12);
+        }
+        // a quarter point lies on the y axis of the plane
+        let quarter = circle.eval(0.25).unwrap();
+        assert!(norm(&sub(&quarter, &[1.0, 2.0, 5.0])) < 1e-12);
+
+        // arcs from 30 to 250 degrees take three segments, a wrapped one from 300 to 20 one
+        for &(start, end, segments) in &[(30.0_f64, 250.0_f64, 3), (300.0, 20.0, 1)] {
+            let (start, end) = (start.to_radians(), end.to_radians());
+            let arc =
+                NURBSCurve::arc(&[0.0, 0.0], &[1.0, 0.0], &[0.0, 1.0], 1.5, start, end).unwrap();
+            assert_eq!(arc.control_points().len(), 2 * segments + 1);
+            let first = arc.eval(0.0).unwrap();
+            let last = arc.eval(1.0).unwrap();
+            assert!(norm(&sub(&first, &[1.5 * start.cos(), 1.5 * start.sin()])) < 1e-12);
+            assert!(norm(&sub(&last, &[1.5 * end.cos(), 1.5 * end.sin()])) < 1e-12);
+            for i in 0..=10 {
+                let point = arc.eval(i as f64 / 10.0).unwrap();
+                assert!((norm(&point) - 1.5).abs() < 1e-12);
+            }
+        }
+        assert!(NURBSCurve::arc(&[0.0, 0.0], &[1.0, 0.0], &[1.0, 1.0], 1.0, 0.0, 1.0).is_err());
+        assert!(NURBSCurve::arc(&[0.0, 0.0], &[1.0, 0.0], &[0.0, 1.0], 0.0, 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_ellipse() {
+        let ellipse = NURBSCurve::ellipse(&[0.0, 0.0], &[3.0, 0.0], &[0.0, 1.0]).unwrap();
+        for i in 0..=40 {
+            let p = ellipse.eval(i as f64 / 40.0).unwrap();
+            assert!((p[0] * p[0] / 9.0 + p[1] * p[1] - 1.0).abs() < 1e-12);
+        }
+        let arc =
+            NURBSCurve::elliptical_arc(&[0.0, 0.0], &[3.0, 0.0], &[0.0, 1.0], 0.0, PI).unwrap();
+        assert_eq!(arc.control_points().len(), 5);
+        assert!(norm(&sub(&arc.eval(0.5).unwrap(), &[0.0, 1.0])) < 1e-12);
+        assert!(norm(&sub(&arc.eval(1.0).unwrap(), &[-3.0, 0.0])) < 1e-12);
+        assert!(NURBSCurve::ellipse(&[0.0, 0.0], &[3.0, 0.0], &[6.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_conic_arcs() {
+        // with weight 1 the arc is the parabola y = 1 - x^2 between (-1, 0) and (1, 0)
+        let parabola = NURBSCurve::conic_arc(&[-1.0, 0.0], &[0.0, 2.0], &[1.0, 0.0], 1.0).unwrap();
+        for i in 0..=10 {
+            let p = parabola.eval(i as f64 / 10.0).unwrap();
+            assert!((p[1] - (1.0 - p[0] * p[0])).abs() < 1e-12);
+        }
+        // the weight sqrt(2) / 2 gives the quarter circle
+        let quarter =
+            NURBSCurve::conic_arc(&[1.0, 0.0], &[1.0, 1.0], &[0.0, 1.0], 0.5_f64.sqrt()).unwrap();
+        assert!((norm(&quarter.eval(0.3).unwrap()) - 1.0).abs() < 1e-12);
+        // the tangents of the hyperbola x * y = 1 at (0.5, 2) and (2, 0.5) meet in
+        // (0.8, 0.8), the weight 1.25 makes the arc pass through (1, 1)
+        let hyperbola = NURBSCurve::conic_arc(&[0.5, 2.0], &[0.8, 0.8], &[2.0, 0.5], 1.25).unwrap();
+        assert!(norm(&sub(&hyperbola.eval(0.5).unwrap(), &[1.0, 1.0])) < 1e-12);
+        for i in 0..=10 {
+            let p = hyperbola.eval(i as f64 / 10.0).unwrap();
+            assert!((p[0] * p[1] - 1.0).abs() < 1e-12);
+        }
+        assert!(NURBSCurve::conic_arc(&[0.0, 0.0], &[1.0, 1.0], &[2.0, 0.0], 0.0).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+// nurbs/construction.rs
+// Surfaces built from profile curves: surfaces of revolution, extrusions, ruled and
+// lofted surfaces.
+// Throughout, the u direction of the surface follows the profile curves.
+use std::f64::consts::PI;
+
+use crate::nurbs::fitting::{averaged_knots, averaged_parameters, interpolation_points};
+use crate::nurbs::knots::knot_multiplicity;
+use crate::nurbs::nurbs_surface::transpose;
+use crate::nurbs::{NURBSCurve, NURBSSurface};
+use crate::vector::{add, cross, dot, homogeneous, norm, normalize, scale, sub};
+
+// Brings the curves to their highest degree, the domain [0, 1] and one merged knot vector
+// by degree elevation and knot refinement, so that their control points correspond
+// one to one. None of the curves change their shape.
+pub(crate) fn make_compatible(curves: &[&NURBSCurve]) -> Result<Vec<NURBSCurve>, &'static str> {
+    if curves.is_empty() {
+        return Err("At least one curve is required");
+    }
+    let dimension = curves[0].control_points()[0].len();
+    if curves
+        .iter()
+        .any(|c| c.control_points()[0].len() != dimension)
+    {
+        return Err("All control points must be of the same dimension");
+    }
+    let degree = curves.iter().map(|c| c.degree()).max().unwrap();
+    let mut compatible = Vec::with_capacity(curves.len());
+    for curve in curves {
+        let (start, end) = curve.domain();
+        let clamped = curve.subcurve(start, end)?;
+        let knots = clamped
+            .knots()
+            .iter()
+            .map(|k| (k - start) / (end - start))
+            .collect();
+        let normalized = NURBSCurve::new(
+            clamped.degree(),
+            knots,
+            clamped.weights().to_vec(),
+            clamped.control_points().to_vec(),
+        )?;
+        compatible.push(normalized.elevate_degree(degree - curve.degree())?);
+    }
+
+    let knot_vectors: Vec<&[f64]> = compatible.iter().map(|c| c.knots()).collect();
+    let merged = merged_knots(&knot_vectors, degree);
+    for curve in compatible.iter_mut() {
+        let missing = missing_knots(&merged, curve.knots());
+        if !missing.is_empty() {
+            *curve = curve.refine_knots(&missing)?;
+        }
+    }
+    Ok(compatible)
+}
+
+// The distinct interior knots of knot vectors of the same `degree` and domain, each with
+// the highest multiplicity it has in any of them
+pub(crate) fn merged_knots(knot_vectors: &[&[f64]], degree: usize) -> Vec<(f64, usize)> {
+    let mut merged: Vec<(f64, usize)> = Vec::new();
+    for knots in knot_vectors {
+        for &k in &knots[degree + 1..knots.len() - degree - 1] {
+            let multiplicity = knot_multiplicity(knots, k);
+            match merged.iter_mut().find(|(m, _)| *m == k) {
+                Some(entry) => entry.1 = entry.1.max(multiplicity),
+                None => merged.push((k, multiplicity)),
+            }
+        }
+    }
+    merged.sort_by(|a, b| a.0.total_cmp(&b.0));
+    merged
+}
+
+// The knots that refine `knots` to contain all of the `merged` knots
+pub(crate) fn missing_knots(merged: &[(f64, usize)], knots: &[f64]) -> Vec<f64> {
+    merged
+        .iter()
+        .flat_map(|&(k, s)| std::iter::repeat_n(k, s - knot_multiplicity(knots, k)))
+        .collect()
+}
+
+fn check_3d(curve: &NURBSCurve) -> Result<(), &'static str> {
+    if curve.control_points()[0].len() != 3 {
+        return Err("The profile curve must be three dimensional");
+    }
+    Ok(())
+}
+
+impl NURBSSurface {
+    // Revolves the profile by `angle` (in radians, up to 2 pi) about the axis through
+    // `axis_point` along `axis_direction`, turning counterclockwise seen from the tip of the
+    // direction. Every control point of the profile sweeps the exact rational arc of
+    // NURBSCurve::arc around the axis (The NURBS Book, A8.1), so u follows the profile
+    // and v the rotation on [0, 1].
+    pub fn revolve(
+        profile: &NURBSCurve,
+        axis_point: &[f64],
+        axis_direction: &[f64],
+        angle: f64,
+    ) -> Result<NURBSSurface, &'static str> {
+        check_3d(profile)?;
+        if axis_point.len() != 3 || axis_direction.len() != 3 {
+            return Err("The axis must be three dimensional");
+        }
+        let axis = normalize(axis_direction).ok_or("The axis direction must not vanish")?;
+        if angle <= 0.0 || angle > 2.0 * PI + 1e-12 {
+            return Err("The angle must be between 0 and 2 pi");
+        }
+        // the unit arc in the plane carries the weights and knots of the rotation
+        let arc = NURBSCurve::arc(&[0.0, 0.0], &[1.0, 0.0], &[0.0, 1.0], 1.0, 0.0, angle)?;
+
+        let mut control_points = Vec::with_capacity(profile.control_points().len());
+        let mut weights = Vec::with_capacity(profile.control_points().len());
+        for (point, &weight) in profile.control_points().iter().zip(profile.weights()) {
+            let offset = sub(point, axis_point);
+            let center = add(axis_point, &scale(&axis, dot(&offset, &axis)));
+            let radial = sub(point, &center);
+            let radius = norm(&radial);
+            // points on the axis stay where they are
+            let (x_axis, y_axis) = match normalize(&radial) {
+                Some(x_axis) => {
+                    let y_axis = cross(&axis, &x_axis);
+                    (scale(&x_axis, radius), scale(&y_axis, radius))
+                }
+                None => (vec![0.0; 3], vec![0.0; 3]),
+            };
+            control_points.push(
+                arc.control_points()
+                    .iter()
+                    .map(|a| add(&center, &add(&scale(&x_axis, a[0]), &scale(&y_axis, a[1]))))
+                    .collect(),
+            );
+            weights.push(arc.weights().iter().map(|w| w * weight).collect());
+        }
+        NURBSSurface::new(
+            profile.degree(),
+            2,
+            profile.knots().to_vec(),
+            arc.knots().to_vec(),
+            weights,
+            control_points,
+        )
+    }
+
+    // The general cylinder S(u, v) = C(u) + v * direction for v in [0, 1]
+    pub fn extrude(profile: &NURBSCurve, direction: &[f64]) -> Result<NURBSSurface, &'static str> {
+        check_3d(profile)?;
+        if direction.len() != 3 {
+            return Err("The direction must be three dimensional");
+        }
+        let control_points = profile
+            .control_points()
+            .iter()
+            .map(|p| vec![p.clone(), add(p, direction)])
+            .collect();
+        let weights = profile.weights().iter().map(|&w| vec![w, w]).collect();
+        NURBSSurface::new(
+            profile.degree(),
+            1,
+            profile.knots().to_vec(),
+            vec![0.0, 0.0, 1.0, 1.0],
+            weights,
+            control_points,
+        )
+    }
+
+    // The ruled surface that joins the curves by straight lines, S(u, 0) runs along `first`
+    // and S(u, 1) along `second`. Both curves are made compatible first and are
+    // reparameterized to the domain [0, 1] of u.
+    pub fn ruled(first: &NURBSCurve, second: &NURBSCurve) -> Result<NURBSSurface, &'static str> {
+        check_3d(first)?;
+        check_3d(second)?;
+        let curves = make_compatible(&[first, second])?;
+        let control_points = curves[0]
+            .control_points()
+            .iter()
+            .zip(curves[1].control_points())
+            .map(|(a, b)| vec![a.clone(), b.clone()])
+            .collect();
+        let weights = curves[0]
+            .weights()
+            .iter()
+            .zip(curves[1].weights())
+            .map(|(&a, &b)| vec![a, b])
+            .collect();
+        NURBSSurface::new(
+            curves[0].degree(),
+            1,
+            curves[0].knots().to_vec(),
+            vec![0.0, 0.0, 1.0, 1.0],
+            weights,
+            control_points,
+        )
+    }
+
+    // Skins a surface through the ordered section curves, S(u, v_k) traces the k-th section.
+    // The sections are made compatible and reparameterized to the domain [0, 1] of u, then
+    // every column of their homogeneous control points is interpolated along v with
+    // `degree_v` (The NURBS Book, 10.3). The parameters v_k average the chord lengths
+    // between the control points of consecutive sections.
+    pub fn loft(sections: &[NURBSCurve], degree_v: usize) -> Result<NURBSSurface, &'static str> {
+        if sections.len() < 2 {
+            return Err("At least two sections are required");
+        }
+        if degree_v == 0 || degree_v >= sections.len() {
+            return Err("The degree in v must be positive and below the number of sections");
+        }
+        let sections = make_compatible(&sections.iter().collect::<Vec<_>>())?;
+        let columns: Vec<Vec<Vec<f64>>> = transpose(
+            &sections
+                .iter()
+                .map(|c| c.control_points().to_vec())
+                .collect::<Vec<_>>(),
+        );
+        let params_v = averaged_parameters(&columns)?;
+        if params_v.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Consecutive sections must not coincide");
+        }
+        skin(&sections, &params_v, degree_v)
+    }
+}
+
+// Interpolates the homogeneous control points of the compatible `sections` along v
+// at the increasing parameters `params_v` in [0, 1]
+pub(crate) fn skin(
+    sections: &[NURBSCurve],
+    params_v: &[f64],
+    degree_v: usize,
+) -> Result<NURBSSurface, &'static str> {
+    let knots_v = averaged_knots(params_v, degree_v);
+    let net_by_section: Vec<Vec<Vec<f64>>> = sections
+        .iter()
+        .map(|c| homogeneous(c.control_points(), c.weights()))
+        .collect();
+    let net = transpose(&net_by_section)
+        .iter()
+        .map(|column| interpolation_points(column, params_v, degree_v, &knots_v, None, None))
+        .collect::<Result<Vec<_>, _>>()?;
+    NURBSSurface::from_homogeneous_net(
+        sections[0].degree(),
+        degree_v,
+        sections[0].knots().to_vec(),
+        knots_v,
+        &net,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{add, cross, norm, scale, sub};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_revolve() {
+        // a line from the axis outwards and up revolves into a cone around the z axis
+        let line = NURBSCurve::new(
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![1.0, 1.0],
+            vec![vec![0.0, 0.0, 1.0], vec![2.0, 0.0, 3.0]],
+        )
+        .unwrap();
+        let cone =
+            NURBSSurface::revolve(&line, &[0.0, 0.0, 0.0], &[0.0, 0.0, 2.0], 2.0 * PI).unwrap();
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (u, v) = (i as f64 / 10.0, j as f64 / 10.0);
+                let p = cone.eval(u, v).unwrap();
+                assert!((norm(&p[..2]) - 2.0 * u).abs() < 1e-12);
+                assert!((p[2] - 1.0 - 2.0 * u).abs() < 1e-12);
+            }
+        }
+        // a quarter turn of a rational quarter circle in the xz plane gives an eighth of a sphere
+        let quarter = NURBSCurve::arc(
+            &[0.0, 0.0, 0.0],
+            &[1.0, 0.0, 0.0],
+            &[0.0, 0.0, 1.0],
+            1.0,
+            0.0,
+            PI / 2.0,
+        )
+        .unwrap();
+        let octant =
+            NURBSSurface::revolve(&quarter, &[0.0, 0.0, 0.0], &[0.0, 0.0, 1.0], PI / 2.0).unwrap();
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let p = octant.eval(i as f64 / 10.0, j as f64 / 10.0).unwrap();
+                assert!((norm(&p) - 1.0).abs() < 1e-12);
+            }
+        }
+        assert!(norm(&sub(&octant.eval(0.0, 1.0).unwrap(), &[0.0, 1.0, 0.0])) < 1e-12);
+        assert!(NURBSSurface::revolve(&line, &[0.0; 3], &[0.0; 3], PI).is_err());
+        assert!(NURBSSurface::revolve(&line, &[0.0; 3], &[0.0, 0.0, 1.0], 7.0).is_err());
+    }
+
+    #[test]
+    fn test_extrude_and_ruled() {
+        let arc = NURBSCurve::arc(
+            &[0.0, 0.0, 0.0],
+            &[1.0, 0.0, 0.0],
+            &[0.0, 1.0, 0.0],
+            1.0,
+            0.0,
+            PI,
+        )
+        .unwrap();
+        let direction = [0.5, 0.0, 2.0];
+        let cylinder = NURBSSurface::extrude(&arc, &direction).unwrap();
+        for i in 0..=10 {
+            let u = i as f64 / 10.0;
+            let expected = add(&arc.eval(u).unwrap(), &scale(&direction, 0.3));
+            assert!(norm(&sub(&cylinder.eval(u, 0.3).unwrap(), &expected)) < 1e-12);
+        }
+
+        // a cubic line on [2, 5] with an interior knot against the quadratic half circle
+        let line = NURBSCurve::new(
+            3,
+            vec![2.0, 2.0, 2.0, 2.0, 3.0, 5.0, 5.0, 5.0, 5.0],
+            vec![1.0; 5],
+            (0..5).map(|i| vec![i as f64 / 4.0, 0.0, 1.0]).collect(),
+        )
+        .unwrap();
+        let ruled = NURBSSurface::ruled(&arc, &line).unwrap();
+        assert_eq!(ruled.degree_u(), 3);
+        assert_eq!(ruled.domain_u(), (0.0, 1.0));
+        for i in 0..=10 {
+            let u = i as f64 / 10.0;
+            let a = arc.eval(u).unwrap();
+            let b = line.eval(2.0 + 3.0 * u).unwrap();
+            assert!(norm(&sub(&ruled.eval(u, 0.0).unwrap(), &a)) < 1e-12);
+            assert!(norm(&sub(&ruled.eval(u, 1.0).unwrap(), &b)) < 1e-12);
+            // the rulings are straight lines
+            let middle = ruled.eval(u, 0.5).unwrap();
+            assert!(norm(&cross(&sub(&middle, &a), &sub(&b, &a))) < 1e-12);
+        }
+        let planar = NURBSCurve::new(
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![1.0; 2],
+            vec![vec![0.0; 2]; 2],
+        );
+        assert!(NURBSSurface::extrude(&planar.unwrap(), &direction).is_err());
+    }
+
+    #[test]
+    fn test_loft() {
+        // circles of the radii 1, 2 and 1 at the heights 0, 1 and 2 and a cubic section
+        let circle = |radius: f64, height: f64| {
+            NURBSCurve::circle(
+                &[0.0, 0.0, height],
+                &[1.0, 0.0, 0.0],
+                &[0.0, 1.0, 0.0],
+                radius,
+            )
+            .unwrap()
+        };
+        let sections = [circle(1.0, 0.0), circle(2.0, 1.0), circle(1.0, 2.0)];
+        let vase = NURBSSurface::loft(&sections, 2).unwrap();
+        // the sections are symmetric, so the middle one lies at v = 0.5
+        for (section, v) in sections.iter().zip([0.0, 0.5, 1.0]) {
+            for i in 0..=20 {
+                let u = i as f64 / 20.0;
+                let expected = section.eval(u).unwrap();
+                assert!(norm(&sub(&vase.eval(u, v).unwrap(), &expected)) < 1e-12);
+            }
+        }
+        // the surface stays rotationally symmetric between the sections
+        let r = |u: f64| norm(&vase.eval(u, 0.3).unwrap()[..2]);
+        assert!((r(0.1) - r(0.6)).abs() < 1e-12);
+
+        let cubic = NURBSCurve::new(
+            3,
+            vec![0.0, 0.0, 0.0, 0.0, 0.4, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0; 5],
+            (0..5).map(|i| vec![i as f64 - 2.0, 0.0, 3.0]).collect(),
+        )
+        .unwrap();
+        let mixed = NURBSSurface::loft(&[sections[0].clone(), cubic.clone()], 1).unwrap();
+        assert_eq!(mixed.degree_u(), 3);
+        for i in 0..=10 {
+            let u = i as f64 / 10.0;
+            let end = mixed.eval(u, 1.0).unwrap();
+            assert!(norm(&sub(&end, &cubic.eval(u).unwrap())) < 1e-12);
+        }
+        assert!(NURBSSurface::loft(&sections, 3).is_err());
+        assert!(NURBSSurface::loft(&sections[..1], 1).is_err());
+        assert!(NURBSSurface::loft(&[cubic.clone(), cubic], 1).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+// nurbs/coons.rs
+// Surfaces spanned by networks of curves: bilinearly blended Coons patches bounded by four
+// curves and Gordon surfaces through a grid of u- and v-curves. Both are the Boolean sum
+// L_u + L_v - T of the loft through the u-curves, the loft through the v-curves and the
+// tensor product interpolant of their intersections (The NURBS Book, 10.5). Summing the
+// control points of the three surfaces is only exact for non-rational curves.
+use crate::nurbs::construction::{make_compatible, merged_knots, missing_knots, skin};
+use crate::nurbs::fitting::{averaged_knots, interpolation_points};
+use crate::nurbs::nurbs_surface::transpose;
+use crate::nurbs::{NURBSCurve, NURBSSurface};
+use crate::vector::{add, norm, sub};
+
+// how far curves may miss each other at their intersections, relative to the size of
+// the network
+const NETWORK_TOLERANCE: f64 = 1e-6;
+
+// Makes the curves compatible and checks that they are non-rational,
+// constant weights are dropped since they do not change the curve
+fn polynomial(curves: &[&NURBSCurve]) -> Result<Vec<NURBSCurve>, &'static str> {
+    make_compatible(curves)?
+        .into_iter()
+        .map(|curve| {
+            let w = curve.weights()[0];
+            if curve.weights().iter().any(|&wi| (wi - w).abs() > 1e-12 * w) {
+                return Err("The curves must not be rational");
+            }
+            NURBSCurve::new(
+                curve.degree(),
+                curve.knots().to_vec(),
+                vec![1.0; curve.weights().len()],
+                curve.control_points().to_vec(),
+            )
+        })
+        .collect()
+}
+
+fn network_tolerance(curves: &[NURBSCurve]) -> f64 {
+    let size = curves
+        .iter()
+        .flat_map(|c| c.control_points())
+        .map(|p| norm(p))
+        .fold(0.0, f64::max);
+    NETWORK_TOLERANCE * (1.0 + size)
+}
+
+// Whether the u-curve k and the v-curve l meet at (u_params[l], v_params[k]) for all k, l
+fn network_meets(
+    u_curves: &[NURBSCurve],
+    v_curves: &[NURBSCurve],
+    u_params: &[f64],
+    v_params: &[f64],
+    tolerance: f64,
+) -> Result<bool, &'static str> {
+    for (u_curve, &v) in u_curves.iter().zip(v_params) {
+        for (v_curve, &u) in v_curves.iter().zip(u_params) {
+            if norm(&sub(&u_curve.eval(u)?, &v_curve.eval(v)?)) > tolerance {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+// Exchanges the u and v directions of a surface
+fn swap_uv(surface: &NURBSSurface) -> Result<NURBSSurface, &'static str> {
+    NURBSSurface::from_homogeneous_net(
+        surface.degree_v(),
+        surface.degree_u(),
+        surface.knots_v().to_vec(),
+        surface.knots_u().to_vec(),
+        &transpose(&surface.homogeneous_net()),
+    )
+}
+
+// The Boolean sum of the compatible non-rational u-curves at `v_params` and v-curves
+// at `u_params`. Both directions are interpolated with the number of curves less one
+// as degree, but at most cubically.
+fn boolean_sum(
+    u_curves: &[NURBSCurve],
+    v_curves: &[NURBSCurve],
+    u_params: &[f64],
+    v_params: &[f64],
+) -> Result<NURBSSurface, &'static str> {
+    let degree_u = (v_curves.len() - 1).min(3);
+    let degree_v = (u_curves.len() - 1).min(3);
+    let loft_u = skin(u_curves, v_params, degree_v)?;
+    let loft_v = swap_uv(&skin(v_curves, u_params, degree_u)?)?;
+    let knots_u = averaged_knots(u_params, degree_u);
+    let intersections = u_curves
+        .iter()
+        .map(|curve| {
+            let points = u_params
+                .iter()
+                .map(|&u| curve.eval(u))
+                .collect::<Result<Vec<_>, _>>()?;
+            let control_points =
+                interpolation_points(&points, u_params, degree_u, &knots_u, None, None)?;
+            NURBSCurve::new(
+                degree_u,
+                knots_u.clone(),
+                vec![1.0; control_points.len()],
+                control_points,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let tensor = skin(&intersections, v_params, degree_v)?;
+
+    // bring the three surfaces to common degrees and knots
+    let mut surfaces = [loft_u, loft_v, tensor];
+    let degree_u = surfaces.iter().map(|s| s.degree_u()).max().unwrap();
+    let degree_v = surfaces.iter().map(|s| s.degree_v()).max().unwrap();
+    for surface in surfaces.iter_mut() {
+        if surface.degree_u() < degree_u {
+            *surface = surface.elevate_degree_u(degree_u - surface.degree_u())?;
+        }
+        if surface.degree_v() < degree_v {
+            *surface = surface.elevate_degree_v(degree_v - surface.degree_v())?;
+        }
+    }
+    let merged_u = merged_knots(
+        &surfaces.iter().map(|s| s.knots_u()).collect::<Vec<_>>(),
+        degree_u,
+    );
+    let merged_v = merged_knots(
+        &surfaces.iter().map(|s| s.knots_v()).collect::<Vec<_>>(),
+        degree_v,
+    );
+    for surface in surfaces.iter_mut() {
+        let missing = missing_knots(&merged_u, surface.knots_u());
+        if !missing.is_empty() {
+            *surface = surface.refine_knots_u(&missing)?;
+        }
+        let missing = missing_knots(&merged_v, surface.knots_v());
+        if !missing.is_empty() {
+            *surface = surface.refine_knots_v(&missing)?;
+        }
+    }
+
+    let [loft_u, loft_v, tensor] = &surfaces;
+    let control_points: Vec<Vec<Vec<f64>>> = loft_u
+        .control_points()
+        .iter()
+        .zip(loft_v.control_points())
+        .zip(tensor.control_points())
+        .map(|((a, b), c)| {
+            a.iter()
+                .zip(b)
+                .zip(c)
+                .map(|((a, b), c)| sub(&add(a, b), c))
+                .collect()
+        })
+        .collect();
+    let weights = vec![vec![1.0; control_points[0].len()]; control_points.len()];
+    NURBSSurface::new(
+        degree_u,
+        degree_v,
+        loft_u.knots_u().to_vec(),
+        loft_u.knots_v().to_vec(),
+        weights,
+        control_points,
+    )
+}
+
+impl NURBSSurface {
+    // The bilinearly blended Coons patch bounded by `bottom` = S(u, 0), `top` = S(u, 1),
+    // `left` = S(0, v) and `right` = S(1, v). The curves must be non-rational and meet at
+    // the corners. Opposite curves are reparameterized to [0, 1] and made compatible,
+    // and the patch reproduces all four of them.
+    pub fn coons(
+        bottom: &NURBSCurve,
+        top: &NURBSCurve,
+        left: &NURBSCurve,
+        right: &NURBSCurve,
+    ) -> Result<NURBSSurface, &'static str> {
+        let u_curves = polynomial(&[bottom, top])?;
+        let v_curves = polynomial(&[left, right])?;
+        let tolerance = network_tolerance(&[u_curves.clone(), v_curves.clone()].concat());
+        if !network_meets(&u_curves, &v_curves, &[0.0, 1.0], &[0.0, 1.0], tolerance)? {
+            return Err("The boundary curves must meet at the corners");
+        }
+        boolean_sum(&u_curves, &v_curves, &[0.0, 1.0], &[0.0, 1.0])
+    }
+
+    // The Gordon surface through a network of non-rational curves: the `u_curves` ordered
+    // along v and the `v_curves` ordered along u. The first and last curves of each kind
+    // bound the network, so every v-curve runs from the first u-curve to the last one and
+    // the other way around. Every u-curve has to cross every v-curve at the same
+    // parameters, S(u_l, v_k) being the intersection of the k-th u-curve and the l-th
+    // v-curve. All curves are reparameterized to [0, 1] and the surface reproduces them.
+    pub fn gordon(
+        u_curves: &[NURBSCurve],
+        v_curves: &[NURBSCurve],
+    ) -> Result<NURBSSurface, &'static str> {
+        if u_curves.len() < 2 || v_curves.len() < 2 {
+            return Err("At least two curves are required in each direction");
+        }
+        let u_curves = polynomial(&u_curves.iter().collect::<Vec<_>>())?;
+        let v_curves = polynomial(&v_curves.iter().collect::<Vec<_>>())?;
+        let tolerance = network_tolerance(&[u_curves.clone(), v_curves.clone()].concat());
+        // the parameters of the intersections with the first curve of the other kind
+        let crossings = |curve: &NURBSCurve, others: &[NURBSCurve]| {
+            let mut params = others
+                .iter()
+                .map(|other| Ok(curve.closest_point(&other.eval(0.0)?)?.t))
+                .collect::<Result<Vec<f64>, &'static str>>()?;
+            let last = params.len() - 1;
+            params[0] = 0.0;
+            params[last] = 1.0;
+            if params.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err("The curves of the network must be ordered");
+            }
+            Ok(params)
+        };
+        let u_params = crossings(&u_curves[0], &v_curves)?;
+        let v_params = crossings(&v_curves[0], &u_curves)?;
+        if !network_meets(&u_curves, &v_curves, &u_params, &v_params, tolerance)? {
+            return Err("The curves of the network must intersect at common parameters");
+        }
+        boolean_sum(&u_curves, &v_curves, &u_params, &v_params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{norm, sub};
+
+    fn curve(degree: usize, control_points: Vec<Vec<f64>>) -> NURBSCurve {
+        let n = control_points.len();
+        let mut knots = vec![0.0; degree + 1];
+        knots.extend((1..n - degree).map(|i| i as f64 / (n - degree) as f64));
+        knots.extend(vec![1.0; degree + 1]);
+        NURBSCurve::new(degree, knots, vec![1.0; n], control_points).unwrap()
+    }
+
+    #[test]
+    fn test_coons() {
+        // a bulging quadratic and a cubic with an interior knot against two lines
+        let bottom = curve(
+            2,
+            vec![
+                vec![0.0, 0.0, 0.0],
+                vec![0.5, -0.2, 1.0],
+                vec![1.0, 0.0, 0.0],
+            ],
+        );
+        let top = curve(1, vec![vec![0.0, 1.0, 0.0], vec![1.0, 1.0, 0.5]]);
+        let left = curve(
+            3,
+            vec![
+                vec![0.0, 0.0, 0.0],
+                vec![0.1, 0.2, 0.3],
+                vec![0.0, 0.5, -0.2],
+                vec![-0.1, 0.8, 0.1],
+                vec![0.0, 1.0, 0.0],
+            ],
+        );
+        let right = curve(1, vec![vec![1.0, 0.0, 0.0], vec![1.0, 1.0, 0.5]]);
+        let patch = NURBSSurface::coons(&bottom, &top, &left, &right).unwrap();
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            for (point, expected) in [
+                (patch.eval(t, 0.0), bottom.eval(t)),
+                (patch.eval(t, 1.0), top.eval(t)),
+                (patch.eval(0.0, t), left.eval(t)),
+                (patch.eval(1.0, t), right.eval(t)),
+            ] {
+                assert!(norm(&sub(&point.unwrap(), &expected.unwrap())) < 1e-12);
+            }
+        }
+
+        // four straight sides give the bilinear patch
+        let line = |a: [f64; 3], b: [f64; 3]| curve(1, vec![a.to_vec(), b.to_vec()]);
+        let flat = NURBSSurface::coons(
+            &line([0.0, 0.0, 0.0], [2.0, 0.0, 0.0]),
+            &line([0.0, 1.0, 0.0], [2.0, 1.0, 1.0]),
+            &line([0.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            &line([2.0, 0.0, 0.0], [2.0, 1.0, 1.0]),
+        )
+        .unwrap();
+        let p = flat.eval(0.25, 0.5).unwrap();
+        assert!(norm(&sub(&p, &[0.5, 0.5, 0.125])) < 1e-12);
+
+        assert!(NURBSSurface::coons(&bottom, &top, &left, &bottom).is_err());
+        let arc = NURBSCurve::conic_arc(&[1.0, 0.0, 0.0], &[1.5, 0.5, 0.0], &[1.0, 1.0, 0.5], 0.8);
+        assert!(NURBSSurface::coons(&bottom, &top, &left, &arc.unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_gordon() {
+        // iso curves of the surface (u, v, u^2 + u v^2), which the Gordon surface reproduces
+        let u_curve = |v: f64| {
+            curve(
+                2,
+                vec![
+                    vec![0.0, v, 0.0],
+                    vec![0.5, v, 0.5 * v * v],
+                    vec![1.0, v, 1.0 + v * v],
+                ],
+            )
+        };
+        let v_curve = |u: f64| {
+            curve(
+                2,
+                vec![
+                    vec![u, 0.0, u * u],
+                    vec![u, 0.5, u * u],
+                    vec![u, 1.0, u * u + u],
+                ],
+            )
+        };
+        let u_curves: Vec<NURBSCurve> = [0.0, 0.3, 0.7, 1.0].iter().map(|&v| u_curve(v)).collect();
+        let v_curves: Vec<NURBSCurve> = [0.0, 0.5, 1.0].iter().map(|&u| v_curve(u)).collect();
+        let gordon = NURBSSurface::gordon(&u_curves, &v_curves).unwrap();
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (u, v) = (i as f64 / 10.0, j as f64 / 10.0);
+                let p = gordon.eval(u, v).unwrap();
+                assert!(norm(&sub(&p, &[u, v, u * u + u * v * v])) < 1e-10);
+            }
+        }
+
+        let mut shuffled = v_curves.clone();
+        shuffled.swap(0, 1);
+        assert!(NURBSSurface::gordon(&u_curves, &shuffled).is_err());
+        let mut bent = u_curves.clone();
+        bent[1] = u_curve(0.3)
+            .elevate_degree(1)
+            .unwrap()
+            .insert_knot(0.5, 1)
+            .unwrap();
+        assert!(NURBSSurface::gordon(&bent, &v_curves).is_ok());
+        bent[2] = curve(1, vec![vec![0.0, 0.7, 0.0], vec![1.0, 0.7, 1.49]]);
+        assert!(NURBSSurface::gordon(&bent, &v_curves).is_err());
+        assert!(NURBSSurface::gordon(&u_curves[..1], &v_curves).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+// nurbs/curve_geometry.rs
+// Differential geometry of NURBS curves built on NURBSCurve::derivatives()
+use crate::nurbs::NURBSCurve;
+use crate::vector::{cross, dot, norm, normalize, scale, sub};
+
+// An orthonormal frame attached to the curve point at parameter `t`.
+// For the Frenet frame `normal` points towards the center of curvature,
+// for rotation minimizing frames it is transported along the curve without twisting.
+// `binormal` always completes the right handed system tangent x normal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub t: f64,
+    pub point: Vec<f64>,
+    pub tangent: Vec<f64>,
+    pub normal: Vec<f64>,
+    pub binormal: Vec<f64>,
+}
+
+impl NURBSCurve {
+    // The (unsigned) curvature at `t` in any dimension,
+    //  k = sqrt(|C'|^2 |C''|^2 - (C' . C'')^2) / |C'|^3
+    pub fn curvature(&self, t: f64) -> Result<f64, &'static str> {
+        let ders = self.derivatives(t, 2)?;
+        let (d1, d2) = (&ders[1], &ders[2]);
+        let speed = norm(d1);
+        if speed <= f64::EPSILON * self.polygon_speed() {
+            return Err("The tangent vanishes at t");
+        }
+        let area = (dot(d1, d1) * dot(d2, d2) - dot(d1, d2).powi(2)).max(0.0);
+        Ok(area.sqrt() / speed.powi(3))
+    }
+
+    // The signed curvature of a planar curve at `t`, positive when the curve turns
+    // counterclockwise, k = (x' y'' - y' x'') / |C'|^3
+    pub fn signed_curvature(&self, t: f64) -> Result<f64, &'static str> {
+        let ders = self.derivatives(t, 2)?;
+        if ders[0].len() != 2 {
+            return Err("Signed curvature is only defined for 2D curves");
+        }
+        let (d1, d2) = (&ders[1], &ders[2]);
+        let speed = norm(d1);
+        if speed <= f64::EPSILON * self.polygon_speed() {
+            return Err("The tangent vanishes at t");
+        }
+        Ok((d1[0] * d2[1] - d1[1] * d2[0]) / speed.powi(3))
+    }
+
+    // The speed the control polygon suggests, its longest leg times the degree over the
+    // length of the domain, as the scale below which |C'| counts as vanishing
+    fn polygon_speed(&self) -> f64 {
+        let (start, end) = self.domain();
+        let leg = self
+            .control_points()
+            .windows(2)
+            .map(|pair| norm(&sub(&pair[1], &pair[0])))
+            .fold(0.0, f64::max);
+        leg * self.degree() as f64 / (end - start)
+    }
+
+    // The torsion of a 3D curve at `t`, tau = (C' x C'') . C''' / |C' x C''|^2
+    pub fn torsion(&self, t: f64) -> Result<f64, &'static str> {
+        let ders = self.derivatives(t, 3)?;
+        if ders[0].len() != 3 {
+            return Err("Torsion is only defined for 3D curves");
+        }
+        let c = cross(&ders[1], &ders[2]);
+        let c2 = dot(&c, &c);
+        // relative to |C'|^2 |C''|^2, which bounds |C' x C''|^2, so that the test does not
+        // depend on the scale of the curve
+        if c2 <= f64::EPSILON * dot(&ders[1], &ders[1]) * dot(&ders[2], &ders[2]) {
+            return Err("Torsion is undefined where the curvature vanishes");
+        }
+        Ok(dot(&c, &ders[3]) / c2)
+    }
+
+    // The Frenet-Serret frame (T, N, B) of a 3D curve at `t`.
+    // Fails on straight parts of the curve where the normal is undefined,
+    // use rotation_minimizing_frames() for those.
+    pub fn frenet_frame(&self, t: f64) -> Result<Frame, &'static str> {
+        let ders = self.derivatives(t, 2)?;
+        if ders[0].len() != 3 {
+            return Err("Frenet frames are only defined for 3D curves");
+        }
+        let tangent = normalize(&ders[1]).ok_or("The tangent vanishes at t")?;
+        let binormal = normalize(&cross(&ders[1], &ders[2]))
+            .ok_or("The normal is undefined where the curvature vanishes")?;
+        let normal = cross(&binormal, &tangent);
+        Ok(Frame {
+            t,
+            point: ders[0].clone(),
+            tangent,
+            normal,
+            binormal,
+        })
+    }
+
+    // Rotation minimizing (parallel transport) frames of a 3D curve at `samples` parameters
+    // spaced uniformly over the domain, computed with the double reflection method
+    // (Wang et al., "Computation of Rotation Minimizing Frames", 2008).
+    // The first frame is the Frenet frame if it exists, otherwise its normal is chosen
+    // perpendicular to the tangent. The tangent must not vanish at any sample.
+    pub fn rotation_minimizing_frames(&self, samples: usize) -> Result<Vec<Frame>, &'static str> {
+        if samples < 2 {
+            return Err("At least two samples are needed");
+        }
+        let (start, end) = self.domain();
+        let params: Vec<f64> = (0..samples)
+            .map(|i| start + (end - start) * i as f64 / (samples - 1) as f64)
+            .collect();
+        self.rotation_minimizing_frames_at(&params)
+    }
+
+    // Rotation minimizing frames at the given increasing parameters, see rotation_minimizing_frames()
+    pub fn rotation_minimizing_frames_at(
+        &self,
+        params: &[f64],
+    ) -> Result<Vec<Frame>, &'static str> {
+        if params.is_empty() {
+            return Err("At least one parameter is needed");
+        }
+        let first = match self.frenet_frame(params[0]) {
+            Ok(frame) => frame,
+            Err(_) => {
+                let ders = self.derivatives(params[0], 1)?;
+                if ders[0].len() != 3 {
+                    return Err("Rotation minimizing frames are only defined for 3D curves");
+                }
+                let tangent = normalize(&ders[1]).ok_or("The tangent vanishes at t")?;
+                // start from the coordinate axis least aligned with the tangent
+                let mut axis = vec![0.0; 3];
+                let k = (0..3)
+                    .min_by(|&a, &b| tangent[a].abs().total_cmp(&tangent[b].abs()))
+                    .unwrap_or(0);
+                axis[k] = 1.0;
+                let normal = normalize(&sub(&axis, &scale(&tangent, dot(&axis, &tangent))))
+                    .ok_or("The tangent vanishes at t")?;
+                let binormal = cross(&tangent, &normal);
+                Frame {
+                    t: params[0],
+                    point: ders[0].clone(),
+                    tangent,
+                    normal,
+                    binormal,
+                }
+            }
+        };
+
+        let mut frames = vec![first];
+        for &t in &params[1..] {
+            let prev = &frames[frames.len() - 1];
+            let ders = self.derivatives(t, 1)?;
+            let tangent = normalize(&ders[1]).ok_or("The tangent vanishes at t")?;
+            // reflect the previous frame in the bisecting plane of the two points ...
+            let v1 = sub(&ders[0], &prev.point);
+            let c1 = dot(&v1, &v1);
+            let (normal_l, tangent_l) = if c1 < f64::EPSILON {
+                (prev.normal.clone(), prev.tangent.clone())
+            } else {
+                (
+                    sub(&prev.normal, &scale(&v1, 2.0 / c1 * dot(&v1, &prev.normal))),
+                    sub(
+                        &prev.tangent,
+                        &scale(&v1, 2.0 / c1 * dot(&v1, &prev.tangent)),
+                    ),
+                )
+            };
+            // ... and then so that the reflected tangent meets the new one
+            let v2 = sub(&tangent, &tangent_l);
+            let c2 = dot(&v2, &v2);
+            let normal = if c2 < f64::EPSILON {
+                normal_l
+            } else {
+                sub(&normal_l, &scale(&v2, 2.0 / c2 * dot(&v2, &normal_l)))
+            };
+            let normal = normalize(&normal).ok_or("The tangent vanishes at t")?;
+            let binormal = cross(&tangent, &normal);
+            frames.push(Frame {
+                t,
+                point: ders[0].clone(),
+                tangent,
+                normal,
+                binormal,
+            });
+        }
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::NURBSCurve;
+    use crate::vector::{dot, norm};
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    // quarter circle of radius 2 in the xy plane
+    fn quarter_circle() -> NURBSCurve {
+        NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![1.0, FRAC_1_SQRT_2, 1.0],
+            vec![
+                vec![2.0, 0.0, 0.0],
+                vec![2.0, 2.0, 0.0],
+                vec![0.0, 2.0, 0.0],
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_circle_curvature() {
+        let circle = quarter_circle();
+        let planar = NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![1.0, FRAC_1_SQRT_2, 1.0],
+            vec![vec![2.0, 0.0], vec![2.0, 2.0], vec![0.0, 2.0]],
+        )
+        .unwrap();
+        for &t in &[0.0, 0.3, 0.5, 1.0] {
+            assert!((circle.curvature(t).unwrap() - 0.5).abs() < 1e-12);
+            assert!((planar.signed_curvature(t).unwrap() - 0.5).abs() < 1e-12);
+            assert!(circle.torsion(t).unwrap().abs() < 1e-12);
+            let frame = circle.frenet_frame(t).unwrap();
+            // the normal points to the center and the binormal along +z
+            let radial: Vec<f64> = frame.point.iter().map(|x| -x / 2.0).collect();
+            assert!((dot(&frame.normal, &radial) - 1.0).abs() < 1e-12);
+            assert!((frame.binormal[2] - 1.0).abs() < 1e-12);
+        }
+        assert!(circle.signed_curvature(0.5).is_err());
+        // curvature scales inversely with the curve, also for tiny curves
+        let tiny = NURBSCurve::new(
+            2,
+            planar.knots().to_vec(),
+            planar.weights().to_vec(),
+            vec![vec![2e-17, 0.0], vec![2e-17, 2e-17], vec![0.0, 2e-17]],
+        )
+        .unwrap();
+        assert!((tiny.curvature(0.3).unwrap() * 1e-17 - 0.5).abs() < 1e-9);
+        assert!((tiny.signed_curvature(0.3).unwrap() * 1e-17 - 0.5).abs() < 1e-9);
+        // a cusp where the control points coincide
+        let cusp = NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![1.0; 3],
+            vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 0.0]],
+        )
+        .unwrap();
+        assert!(cusp.curvature(1.0).is_err() && cusp.signed_curvature(1.0).is_err());
+    }
+
+    #[test]
+    fn test_helix_torsion() {
+        // a cubic that leaves the plane has nonzero torsion
+        let curve = NURBSCurve::new(
+            3,
+            vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0; 4],
+            vec![
+                vec![0.0, 0.0, 0.0],
+                vec![1.0, 0.0, 0.0],
+                vec![1.0, 1.0, 0.0],
+                vec![1.0, 1.0, 1.0],
+            ],
+        )
+        .unwrap();
+        // the polynomial (3t - 3t^2 + t^3, 3t^2 - 2t^3, t^3) has torsion 1/3 at t = 0
+        assert!((curve.torsion(0.0).unwrap() - 1.0 / 3.0).abs() < 1e-12);
+        // torsion scales inversely with the curve, also for tiny curves
+        let points: Vec<Vec<f64>> = curve
+            .control_points()
+            .iter()
+            .map(|p| p.iter().map(|x| x * 1e-6).collect())
+            .collect();
+        let tiny = NURBSCurve::new(3, curve.knots().to_vec(), vec![1.0; 4], points).unwrap();
+        assert!((tiny.torsion(0.0).unwrap() * 1e-6 - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rotation_minimizing_frames() {
+        // a straight line has no Frenet frame, but rotation minimizing frames do not twist
+        let line = NURBSCurve::new(
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![1.0, 1.0],
+            vec![vec![0.0, 0.0, 0.0], vec![0.0, 0.0, 3.0]],
+        )
+        .unwrap();
+        assert!(line.frenet_frame(0.5).is_err());
+        let frames = line.rotation_minimizing_frames(5).unwrap();
+        assert_eq!(frames.len(), 5);
+        for frame in &frames {
+            assert_eq!(frame.normal, frames[0].normal);
+        }
+
+        // along a planar circle the transported normal stays in the plane
+        let frames = quarter_circle().rotation_minimizing_frames(20).unwrap();
+        for frame in &frames {
+            assert!((norm(&frame.normal) - 1.0).abs() < 1e-12);
+            assert!(dot(&frame.normal, &frame.tangent).abs() < 1e-12);
+            assert!(frame.normal[2].abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,449 @@
+// nurbs/degree.rs
+// Degree elevation and reduction. Both split the curve into Bézier segments, change the
+// degree of every segment and join the segments again, removing the knots that were
+// only needed for the splitting afterwards.
+use crate::binomial;
+use crate::nurbs::bezier::bezier_segments;
+use crate::nurbs::knots::{homogeneous_tolerance, knot_multiplicity, remove_knot_rows};
+use crate::nurbs::{NURBSCurve, NURBSSurface, Rows};
+use crate::vector::{cartesian, norm, sub};
+
+// Raises the degree of a Bézier segment by `times`,
+//  Qi = sum_j binomial(p, j) * binomial(times, i - j) / binomial(p + times, i) * Pj
+fn elevate_bezier(points: &[Vec<f64>], times: usize) -> Vec<Vec<f64>> {
+    let p = points.len() - 1;
+    (0..=p + times)
+        .map(|i| {
+            let mut q = vec![0.0; points[0].len()];
+            for (j, point) in points
+                .iter()
+                .enumerate()
+                .take(i.min(p) + 1)
+                .skip(i.saturating_sub(times))
+            {
+                let a = binomial(p, j) * binomial(times, i - j) / binomial(p + times, i);
+                for (q, x) in q.iter_mut().zip(point) {
+                    *q += a * x;
+                }
+            }
+            q
+        })
+        .collect()
+}
+
+// Lowers the degree of a homogeneous Bézier segment by one. The new control points are
+// solved from the degree elevation formula starting at both ends, which keeps the end
+// points and averages the two solutions in the middle (The NURBS Book, section 5.6).
+// Returns the new points and a bound on the distance between the two rational segments.
+fn reduce_bezier(points: &[Vec<f64>]) -> (Vec<Vec<f64>>, f64) {
+    let p = points.len() - 1;
+    let r = (p - 1) / 2;
+    let mut q = vec![Vec::new(); p];
+    q[0] = points[0].clone();
+    q[p - 1] = points[p].clone();
+    for i in 1..=r {
+        let alpha = i as f64 / p as f64;
+        q[i] = points[i]
+            .iter()
+            .zip(&q[i - 1])
+            .map(|(x, y)| (x - alpha * y) / (1.0 - alpha))
+            .collect();
+    }
+    for i in (r + 1..p - 1).rev() {
+        let alpha = (i + 1) as f64 / p as f64;
+        q[i] = points[i + 1]
+            .iter()
+            .zip(&q[i + 1])
+            .map(|(x, y)| (x - (1.0 - alpha) * y) / alpha)
+            .collect();
+    }
+    if p % 2 == 1 && p > 1 {
+        // for odd degrees the middle point is solved from both sides
+        let alpha = (r + 1) as f64 / p as f64;
+        let right: Vec<f64> = points[r + 1]
+            .iter()
+            .zip(&q[r + 1])
+            .map(|(x, y)| (x - (1.0 - alpha) * y) / alpha)
+            .collect();
+        q[r] = q[r]
+            .iter()
+            .zip(&right)
+            .map(|(a, b)| 0.5 * (a + b))
+            .collect();
+    }
+    // With the homogeneous segments A / w and A' / w' of the original and of the reduced
+    // segment elevated back, C - C' = ((A - A') - C' (w - w')) / w. Each term is bounded by
+    // the control points, since the Bernstein polynomials form a partition of unity.
+    let elevated = elevate_bezier(&q, 1);
+    let (mut delta_a, mut delta_w, mut c_max, mut w_min) = (0.0_f64, 0.0_f64, 0.0_f64, f64::MAX);
+    for (e, x) in elevated.iter().zip(points) {
+        let (&ew, ea) = e.split_last().unwrap();
+        let (&xw, xa) = x.split_last().unwrap();
+        delta_a = delta_a.max(norm(&sub(ea, xa)));
+        delta_w = delta_w.max((ew - xw).abs());
+        c_max = c_max.max(norm(ea) / ew.abs().max(f64::EPSILON));
+        w_min = w_min.min(xw);
+    }
+    let error = if w_min > 0.0 {
+        (delta_a + c_max * delta_w) / w_min
+    } else {
+        f64::INFINITY
+    };
+    (q, error)
+}
+
+// Joins Bézier segments of `degree` between the `breaks` into one knot vector and
+// control polygon, interior knots get multiplicity degree
+fn join_bezier(
+    degree: usize,
+    breaks: &[f64],
+    segments: &[Vec<Vec<f64>>],
+) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let mut knots = vec![breaks[0]; degree + 1];
+    for &b in &breaks[1..breaks.len() - 1] {
+        knots.extend(vec![b; degree]);
+    }
+    knots.extend(vec![breaks[breaks.len() - 1]; degree + 1]);
+    let mut points = segments[0].clone();
+    for segment in &segments[1..] {
+        points.extend_from_slice(&segment[1..]);
+    }
+    (knots, points)
+}
+
+// The distinct interior knots of a knot vector with their multiplicities
+fn interior_knots(degree: usize, knots: &[f64]) -> Vec<(f64, usize)> {
+    let (start, end) = (knots[degree], knots[knots.len() - degree - 1]);
+    let mut interior: Vec<(f64, usize)> = Vec::new();
+    for &k in knots.iter().filter(|&&k| k > start && k < end) {
+        if interior.last().is_none_or(|&(last, _)| last < k) {
+            interior.push((k, knot_multiplicity(knots, k)));
+        }
+    }
+    interior
+}
+
+// Raises the degree of every row of homogeneous control points sharing `knots` by `times`.
+// Interior knots keep their continuity, so their multiplicity grows by `times`.
+pub(crate) fn elevate_rows(
+    degree: usize,
+    knots: &[f64],
+    rows: &[Vec<Vec<f64>>],
+    times: usize,
+) -> (Vec<f64>, Rows) {
+    let new_degree = degree + times;
+    let (breaks, segments) = bezier_segments(degree, knots, rows);
+    let mut new_knots = Vec::new();
+    let mut new_rows = Vec::with_capacity(rows.len());
+    for row in &segments {
+        let elevated: Vec<Vec<Vec<f64>>> = row.iter().map(|s| elevate_bezier(s, times)).collect();
+        let (knots, points) = join_bezier(new_degree, &breaks, &elevated);
+        new_knots = knots;
+        new_rows.push(points);
+    }
+    // the elevation is exact, so the knots introduced by the splitting can be removed
+    // up to rounding errors
+    let scale = rows.iter().flatten().map(|p| norm(p)).fold(0.0, f64::max);
+    let tolerance = 1e-9 * (1.0 + scale);
+    for (k, s) in interior_knots(degree, knots) {
+        let (_, knots, rows) = remove_knot_rows(
+            new_degree,
+            &new_knots,
+            &new_rows,
+            k,
+            degree.saturating_sub(s),
+            tolerance,
+        );
+        new_knots = knots;
+        new_rows = rows;
+    }
+    (new_knots, new_rows)
+}
+
+// Lowers the degree of every row of homogeneous control points sharing `knots` by one,
+// failing if the curves would deviate by more than the geometric `tolerance`.
+// Interior knots try to keep their continuity, so their multiplicity shrinks by one
+// where the remaining tolerance allows it.
+pub(crate) fn reduce_rows(
+    degree: usize,
+    knots: &[f64],
+    rows: &[Vec<Vec<f64>>],
+    tolerance: f64,
+) -> Result<(Vec<f64>, Rows), &'static str> {
+    if degree < 2 {
+        return Err("Degree must be greater than 0");
+    }
+    let new_degree = degree - 1;
+    let (breaks, segments) = bezier_segments(degree, knots, rows);
+    let mut new_knots = Vec::new();
+    let mut new_rows = Vec::with_capacity(rows.len());
+    let mut max_error: f64 = 0.0;
+    for row in &segments {
+        let mut reduced = Vec::with_capacity(row.len());
+        for segment in row {
+            let (points, error) = reduce_bezier(segment);
+            max_error = max_error.max(error);
+            reduced.push(points);
+        }
+        let (knots, points) = join_bezier(new_degree, &breaks, &reduced);
+        new_knots = knots;
+        new_rows.push(points);
+    }
+    if max_error > tolerance {
+        return Err("The degree cannot be reduced within the tolerance");
+    }
+    // split what is left of the tolerance evenly between all knot removals
+    let interior = interior_knots(degree, knots);
+    let removals: usize = interior
+        .iter()
+        .map(|&(_, s)| new_degree.saturating_sub(s - 1))
+        .sum();
+    let (points, weights): (Vec<Vec<Vec<f64>>>, Vec<Vec<f64>>) =
+        new_rows.iter().map(|row| cartesian(row)).unzip();
+    let weights = weights.concat();
+    let points: Vec<&Vec<f64>> = points.iter().flatten().collect();
+    let budget = homogeneous_tolerance(
+        (tolerance - max_error) / removals.max(1) as f64,
+        &weights,
+        &points,
+    );
+    for (k, s) in interior {
+        let (_, knots, rows) = remove_knot_rows(
+            new_degree,
+            &new_knots,
+            &new_rows,
+            k,
+            new_degree.saturating_sub(s - 1),
+            budget,
+        );
+        new_knots = knots;
+        new_rows = rows;
+    }
+    Ok((new_knots, new_rows))
+}
+
+impl NURBSCurve {
+    // Raises the degree by `times` without changing the curve
+    pub fn elevate_degree(&self, times: usize) -> Result<NURBSCurve, &'static str> {
+        let (knots, rows) = elevate_rows(
+            self.degree(),
+            self.knots(),
+            &[self.homogeneous_points()],
+            times,
+        );
+        NURBSCurve::from_homogeneous(self.degree() + times, knots, &rows[0])
+    }
+
+    // Lowers the degree by `times`, so that the result deviates from the curve by no more
+    // than `tolerance`. Fails if the curve cannot be approximated that closely.
+    pub fn reduce_degree(&self, times: usize, tolerance: f64) -> Result<NURBSCurve, &'static str> {
+        if times >= self.degree() {
+            return Err("Degree must be greater than 0");
+        }
+        let tolerance = tolerance / times as f64;
+        let mut knots = self.knots().to_vec();
+        let mut rows = vec![self.homogeneous_points()];
+        for step in 0..times {
+            (knots, rows) = reduce_rows(self.degree() - step, &knots, &rows, tolerance)?;
+        }
+        NURBSCurve::from_homogeneous(self.degree() - times, knots, &rows[0])
+    }
+}
+
+impl NURBSSurface {
+    // Raises the degree in the u direction by `times` without changing the surface
+    pub fn elevate_degree_u(&self, times: usize) -> Result<NURBSSurface, &'static str> {
+        let (knots_u, rows) = elevate_rows(self.degree_u(), self.knots_u(), &self.rows_u(), times);
+        NURBSSurface::from_rows_u(
+            self.degree_u() + times,
+            self.degree_v(),
+            knots_u,
+            self.knots_v().to_vec(),
+            &rows,
+        )
+    }
+
+    // Raises the degree in the v direction by `times` without changing the surface
+    pub fn elevate_degree_v(&self, times: usize) -> Result<NURBSSurface, &'static str> {
+        let (knots_v, net) = elevate_rows(
+            self.degree_v(),
+            self.knots_v(),
+            &self.homogeneous_net(),
+            times,
+        );
+        NURBSSurface::from_homogeneous_net(
+            self.degree_u(),
+            self.degree_v() + times,
+            self.knots_u().to_vec(),
+            knots_v,
+            &net,
+        )
+    }
+
+    // Lowers the degree in the u direction by `times` within `tolerance`,
+    // see NURBSCurve::reduce_degree()
+    pub fn reduce_degree_u(
+        &self,
+        times: usize,
+        tolerance: f64,
+    ) -> Result<NURBSSurface, &'static str> {
+        if times >= self.degree_u() {
+            return Err("Degree must be greater than 0");
+        }
+        let tolerance = tolerance / times as f64;
+        let mut knots_u = self.knots_u().to_vec();
+        let mut rows = self.rows_u();
+        for step in 0..times {
+            (knots_u, rows) = reduce_rows(self.degree_u() - step, &knots_u, &rows, tolerance)?;
+        }
+        NURBSSurface::from_rows_u(
+            self.degree_u() - times,
+            self.degree_v(),
+            knots_u,
+            self.knots_v().to_vec(),
+            &rows,
+        )
+    }
+
+    // Lowers the degree in the v direction by `times` within `tolerance`,
+    // see NURBSCurve::reduce_degree()
+    pub fn reduce_degree_v(
+        &self,
+        times: usize,
+        tolerance: f64,
+    ) -> Result<NURBSSurface, &'static str> {
+        if times >= self.degree_v() {
+            return Err("Degree must be greater than 0");
+        }
+        let tolerance = tolerance / times as f64;
+        let mut knots_v = self.knots_v().to_vec();
+        let mut net = self.homogeneous_net();
+        for step in 0..times {
+            (knots_v, net) = reduce_rows(self.degree_v() - step, &knots_v, &net, tolerance)?;
+        }
+        NURBSSurface::from_homogeneous_net(
+            self.degree_u(),
+            self.degree_v() - times,
+            self.knots_u().to_vec(),
+            knots_v,
+            &net,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{norm, sub};
+
+    fn curve() -> NURBSCurve {
+        NURBSCurve::new(
+            3,
+            vec![0.0, 0.0, 0.0, 0.0, 0.4, 0.4, 0.7, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0, 0.5, 2.0, 1.0, 1.0, 1.5, 1.0],
+            vec![
+                vec![0.0, 0.0, 0.0],
+                vec![1.0, 2.0, 0.0],
+                vec![2.0, 2.0, 1.0],
+                vec![3.0, 0.0, 1.0],
+                vec![4.0, 1.0, 0.0],
+                vec![5.0, 1.0, 2.0],
+                vec![6.0, 0.0, 0.0],
+            ],
+        )
+        .unwrap()
+    }
+
+    fn max_deviation(a: &NURBSCurve, b: &NURBSCurve) -> f64 {
+        (0..=100)
+            .map(|i| {
+                let t = i as f64 / 100.0;
+                norm(&sub(&a.eval(t).unwrap(), &b.eval(t).unwrap()))
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_elevate_degree() {
+        let curve = curve();
+        let elevated = curve.elevate_degree(2).unwrap();
+        assert_eq!(elevated.degree(), 5);
+        // interior knots keep their continuity
+        assert_eq!(
+            elevated.knots(),
+            &[
+                0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.4, 0.4, 0.4, 0.4, 0.7, 0.7, 0.7, 1.0, 1.0, 1.0,
+                1.0, 1.0, 1.0
+            ]
+        );
+        assert!(max_deviation(&curve, &elevated) < 1e-12);
+    }
+
+    #[test]
+    fn test_reduce_degree() {
+        // an elevated curve can be reduced exactly back to its original form
+        let curve = curve();
+        let reduced = curve
+            .elevate_degree(1)
+            .unwrap()
+            .reduce_degree(1, 1e-9)
+            .unwrap();
+        assert_eq!(reduced.degree(), 3);
+        assert_eq!(reduced.knots(), curve.knots());
+        assert!(max_deviation(&curve, &reduced) < 1e-9);
+
+        // after disturbing the elevated curve only a loose tolerance allows the reduction
+        let elevated = curve.elevate_degree(1).unwrap();
+        let mut points = elevated.control_points().to_vec();
+        points[5][1] += 0.01;
+        let disturbed = NURBSCurve::new(
+            4,
+            elevated.knots().to_vec(),
+            elevated.weights().to_vec(),
+            points,
+        )
+        .unwrap();
+        assert!(disturbed.reduce_degree(1, 1e-4).is_err());
+        let loose = disturbed.reduce_degree(1, 0.1).unwrap();
+        assert_eq!(loose.degree(), 3);
+        assert!(max_deviation(&disturbed, &loose) < 0.1);
+        assert!(max_deviation(&disturbed, &loose) > 1e-4);
+        assert!(curve.reduce_degree(3, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_surface_degree() {
+        let knots = vec![0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0];
+        let weights = vec![vec![1.0, 2.0, 1.0, 1.0]; 4];
+        let control_points = (0..4)
+            .map(|i| {
+                (0..4)
+                    .map(|j| vec![i as f64, j as f64, ((i + 2 * j) % 3) as f64])
+                    .collect()
+            })
+            .collect();
+        let surface =
+            NURBSSurface::new(2, 2, knots.clone(), knots, weights, control_points).unwrap();
+        let elevated = surface
+            .elevate_degree_u(1)
+            .unwrap()
+            .elevate_degree_v(2)
+            .unwrap();
+        assert_eq!((elevated.degree_u(), elevated.degree_v()), (3, 4));
+        let reduced = elevated
+            .reduce_degree_u(1, 1e-9)
+            .unwrap()
+            .reduce_degree_v(2, 1e-9)
+            .unwrap();
+        assert_eq!(reduced.knots_u(), surface.knots_u());
+        assert_eq!(reduced.knots_v(), surface.knots_v());
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (u, v) = (i as f64 / 10.0, j as f64 / 10.0);
+                let point = surface.eval(u, v).unwrap();
+                assert!(norm(&sub(&point, &elevated.eval(u, v).unwrap())) < 1e-12);
+                assert!(norm(&sub(&point, &reduced.eval(u, v).unwrap())) < 1e-9);
+            }
+        }
+    }
+}
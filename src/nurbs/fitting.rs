@@ -0,0 +1,642 @@
+// nurbs/fitting.rs
+// Curves and surfaces through or close to given data points
+use crate::linalg::BandMatrix;
+use crate::nurbs::knots::breakpoints;
+use crate::nurbs::nurbs_surface::transpose;
+use crate::nurbs::{NURBSCurve, NURBSSurface};
+use crate::vector::{norm, scale, sub};
+use crate::{bspline_basis_derivatives, find_span};
+
+// How the parameters in [0, 1] are assigned to the data points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parameterization {
+    // equally spaced, ignores the distances between the points
+    Uniform,
+    // proportional to the distances between consecutive points
+    ChordLength,
+    // proportional to the square roots of the distances, which keeps sharp turns
+    // from overshooting (Lee, "Choosing nodes in parametric curve interpolation", 1989)
+    Centripetal,
+}
+
+impl NURBSCurve {
+    // Builds the non-rational curve of `degree` that passes through all `points` in order,
+    // see interpolate_with_tangents()
+    pub fn interpolate(
+        points: &[Vec<f64>],
+        degree: usize,
+        parameterization: Parameterization,
+    ) -> Result<NURBSCurve, &'static str> {
+        NURBSCurve::interpolate_with_tangents(points, degree, parameterization, None, None)
+    }
+
+    // Global interpolation of `points` with a non-rational curve of `degree` on the domain
+    // [0, 1] (The NURBS Book, section 9.2). The points get parameters by `parameterization`,
+    // the interior knots are averages of `degree` consecutive parameters, and the banded
+    // system C(t_k) = Q_k is solved for the control points.
+    // Optional derivatives at the start and end add one control point each, they are
+    // derivatives with respect to the curve parameter, so a unit tangent should be scaled
+    // by about the total chord length of the points.
+    pub fn interpolate_with_tangents(
+        points: &[Vec<f64>],
+        degree: usize,
+        parameterization: Parameterization,
+        start_tangent: Option<&[f64]>,
+        end_tangent: Option<&[f64]>,
+    ) -> Result<NURBSCurve, &'static str> {
+        let params = parameters(points, parameterization)?;
+        if params.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err("Consecutive points must not coincide");
+        }
+        let tangents = [start_tangent, end_tangent];
+        if tangents
+            .iter()
+            .flatten()
+            .any(|d| d.len() != points[0].len())
+        {
+            return Err("The tangents must have the same dimension as the points");
+        }
+        // the parameter list with the ends repeated for every derivative, one per unknown
+        let mut extended = params.clone();
+        if start_tangent.is_some() {
+            extended.insert(0, 0.0);
+        }
+        if end_tangent.is_some() {
+            extended.push(1.0);
+        }
+        if degree == 0 || extended.len() <= degree {
+            return Err("The degree must be positive and below the number of unknowns");
+        }
+        let knots = averaged_knots(&extended, degree);
+
+        let control_points =
+            interpolation_points(points, &params, degree, &knots, start_tangent, end_tangent)?;
+        let weights = vec![1.0; control_points.len()];
+        NURBSCurve::new(degree, knots, weights, control_points)
+    }
+
+    // Least squares approximation of `points` with `control_points` control points,
+    // see approximate_weighted()
+    pub fn approximate(
+        points: &[Vec<f64>],
+        degree: usize,
+        control_points: usize,
+    ) -> Result<NURBSCurve, &'static str> {
+        NURBSCurve::approximate_weighted(points, &vec![1.0; points.len()], degree, control_points)
+    }
+
+    // Fits a non-rational curve of `degree` with `control_points` control points to `points`
+    // by weighted least squares (The NURBS Book, section 9.4.1). The curve starts at the
+    // first and ends at the last point, the points in between are approximated minimizing
+    // the sum of point_weights[k] * |Q_k - C(t_k)|^2 over chord length parameters t_k.
+    // The knots are spread so that every knot span contains parameters (eq. 9.68).
+    pub fn approximate_weighted(
+        points: &[Vec<f64>],
+        point_weights: &[f64],
+        degree: usize,
+        control_points: usize,
+    ) -> Result<NURBSCurve, &'static str> {
+        let params = parameters(points, Parameterization::ChordLength)?;
+        check_point_weights(points, point_weights)?;
+        if degree == 0 || control_points <= degree || control_points > points.len() {
+            return Err("The number of control points must be above the degree and at most the number of points");
+        }
+        let knots = spread_knots(&params, degree, control_points);
+        let control_points = least_squares(points, &params, point_weights, degree, &knots)?;
+        let weights = vec![1.0; control_points.len()];
+        NURBSCurve::new(degree, knots, weights, control_points)
+    }
+
+    // Least squares approximation of `points` within `tolerance`,
+    // see approximate_weighted_within()
+    pub fn approximate_within(
+        points: &[Vec<f64>],
+        degree: usize,
+        tolerance: f64,
+    ) -> Result<NURBSCurve, &'static str> {
+        NURBSCurve::approximate_weighted_within(points, &vec![1.0; points.len()], degree, tolerance)
+    }
+
+    // Approximates `points` like approximate_weighted() with only as many knots as needed
+    // for a deviation |Q_k - C(t_k)| of at most `tolerance` at every point. Starting from a
+    // single Bézier segment, every knot span with a point too far off is split between its
+    // middle points and the curve is fitted again. Fails if no span can be split any more
+    // before the tolerance is met.
+    pub fn approximate_weighted_within(
+        points: &[Vec<f64>],
+        point_weights: &[f64],
+        degree: usize,
+        tolerance: f64,
+    ) -> Result<NURBSCurve, &'static str> {
+        if tolerance <= 0.0 {
+            return Err("The tolerance must be positive");
+        }
+        let params = parameters(points, Parameterization::ChordLength)?;
+        check_point_weights(points, point_weights)?;
+        if degree == 0 || points.len() <= degree {
+            return Err("The degree must be positive and below the number of points");
+        }
+        let mut knots = [vec![0.0; degree + 1], vec![1.0; degree + 1]].concat();
+        loop {
+            let control_points = least_squares(points, &params, point_weights, degree, &knots)?;
+            let weights = vec![1.0; control_points.len()];
+            let curve = NURBSCurve::new(degree, knots.clone(), weights, control_points)?;
+            let mut deviations = Vec::with_capacity(points.len());
+            for (point, &t) in points.iter().zip(&params) {
+                deviations.push(norm(&sub(&curve.eval(t)?, point)));
+            }
+            if deviations.iter().all(|&d| d <= tolerance) {
+                return Ok(curve);
+            }
+
+            let mut new_knots = Vec::new();
+            for span in breakpoints(degree, &knots).windows(2) {
+                let inside: Vec<usize> = (0..params.len())
+                    .filter(|&k| params[k] >= span[0] && (params[k] < span[1] || span[1] == 1.0))
+                    .collect();
+                if inside.len() >= 2 && inside.iter().any(|&k| deviations[k] > tolerance) {
+                    let mid = inside.len() / 2;
+                    let knot = 0.5 * (params[inside[mid - 1]] + params[inside[mid]]);
+                    if knot > span[0] && knot < span[1] {
+                        new_knots.push(knot);
+                    }
+                }
+            }
+            let count = knots.len() - degree - 1 + new_knots.len();
+            if new_knots.is_empty() || count > points.len() {
+                return Err("The points cannot be approximated within the tolerance");
+            }
+            knots.extend(new_knots);
+            knots.sort_by(f64::total_cmp);
+        }
+    }
+}
+
+impl NURBSSurface {
+    // Builds the non-rational surface through a rectangular grid of points, where
+    // grid[k][l] is the k-th point along u and the l-th along v. Tensor product
+    // interpolation (The NURBS Book, A9.4): the parameters are chord length parameters
+    // averaged over all rows and columns, curves are interpolated along u through every
+    // column of the grid and then along v through the resulting control points.
+    pub fn interpolate(
+        grid: &[Vec<Vec<f64>>],
+        degree_u: usize,
+        degree_v: usize,
+    ) -> Result<NURBSSurface, &'static str> {
+        let (params_u, params_v) = grid_parameters(grid)?;
+        if params_u.windows(2).any(|pair| pair[0] == pair[1])
+            || params_v.windows(2).any(|pair| pair[0] == pair[1])
+        {
+            return Err("Consecutive rows and columns of the grid must not coincide");
+        }
+        if degree_u == 0 || degree_v == 0 || grid.len() <= degree_u || grid[0].len() <= degree_v {
+            return Err("The degrees must be positive and below the number of points");
+        }
+        let knots_u = averaged_knots(&params_u, degree_u);
+        let knots_v = averaged_knots(&params_v, degree_v);
+        fit_grid(
+            grid,
+            (&params_u, &knots_u, degree_u),
+            (&params_v, &knots_v, degree_v),
+            |points, params, knots, degree| {
+                interpolation_points(points, params, degree, knots, None, None)
+            },
+        )
+    }
+
+    // Least squares approximation of a rectangular grid of points with control_points_u x
+    // control_points_v control points, the tensor product counterpart of
+    // NURBSCurve::approximate(). The boundary curves start and end at the corner points.
+    pub fn approximate(
+        grid: &[Vec<Vec<f64>>],
+        degree_u: usize,
+        degree_v: usize,
+        control_points_u: usize,
+        control_points_v: usize,
+    ) -> Result<NURBSSurface, &'static str> {
+        let (params_u, params_v) = grid_parameters(grid)?;
+        if degree_u == 0
+            || degree_v == 0
+            || control_points_u <= degree_u
+            || control_points_v <= degree_v
+            || control_points_u > grid.len()
+            || control_points_v > grid[0].len()
+        {
+            return Err("The number of control points must be above the degree and at most the number of points");
+        }
+        let knots_u = spread_knots(&params_u, degree_u, control_points_u);
+        let knots_v = spread_knots(&params_v, degree_v, control_points_v);
+        fit_grid(
+            grid,
+            (&params_u, &knots_u, degree_u),
+            (&params_v, &knots_v, degree_v),
+            |points, params, knots, degree| {
+                least_squares(points, params, &vec![1.0; points.len()], degree, knots)
+            },
+        )
+    }
+}
+
+// Fits curves with `fit` along u through the columns of the grid and then along v through
+// their control points, each direction given as its parameters, knots and degree
+fn fit_grid(
+    grid: &[Vec<Vec<f64>>],
+    (params_u, knots_u, degree_u): (&[f64], &[f64], usize),
+    (params_v, knots_v, degree_v): (&[f64], &[f64], usize),
+    fit: impl Fn(&[Vec<f64>], &[f64], &[f64], usize) -> Result<Vec<Vec<f64>>, &'static str>,
+) -> Result<NURBSSurface, &'static str> {
+    let columns = (0..grid[0].len())
+        .map(|l| {
+            let column: Vec<Vec<f64>> = grid.iter().map(|row| row[l].clone()).collect();
+            fit(&column, params_u, knots_u, degree_u)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let control_points = transpose(&columns)
+        .iter()
+        .map(|row| fit(row, params_v, knots_v, degree_v))
+        .collect::<Result<Vec<_>, _>>()?;
+    let weights = vec![vec![1.0; control_points[0].len()]; control_points.len()];
+    NURBSSurface::new(
+        degree_u,
+        degree_v,
+        knots_u.to_vec(),
+        knots_v.to_vec(),
+        weights,
+        control_points,
+    )
+}
+
+// Chord length parameters along u averaged over all columns of the grid and along v
+// averaged over all rows, degenerate rows or columns whose points coincide are left out
+fn grid_parameters(grid: &[Vec<Vec<f64>>]) -> Result<(Vec<f64>, Vec<f64>), &'static str> {
+    if grid.len() < 2 || grid[0].len() < 2 {
+        return Err("The grid needs at least two points in both directions");
+    }
+    if !grid.iter().all(|row| row.len() == grid[0].len()) {
+        return Err("All rows of the grid must have the same length");
+    }
+    if !grid.iter().flatten().all(|p| p.len() == grid[0][0].len()) {
+        return Err("All points must be of the same dimension");
+    }
+    let params_u = averaged_parameters(&transpose(grid))?;
+    let params_v = averaged_parameters(grid)?;
+    Ok((params_u, params_v))
+}
+
+// Chord length parameters of equally long lines of points averaged over all lines,
+// lines whose points coincide are left out
+pub(crate) fn averaged_parameters(lines: &[Vec<Vec<f64>>]) -> Result<Vec<f64>, &'static str> {
+    let params: Vec<Vec<f64>> = lines
+        .iter()
+        .filter_map(|line| parameters(line, Parameterization::ChordLength).ok())
+        .collect();
+    if params.is_empty() {
+        return Err("The points of the grid must not all coincide");
+    }
+    let count = params.len() as f64;
+    Ok((0..params[0].len())
+        .map(|k| params.iter().map(|p| p[k]).sum::<f64>() / count)
+        .collect())
+}
+
+// The parameters of the data points in [0, 1]
+pub(crate) fn parameters(
+    points: &[Vec<f64>],
+    parameterization: Parameterization,
+) -> Result<Vec<f64>, &'static str> {
+    if points.len() < 2 {
+        return Err("At least two points are needed");
+    }
+    if !points.iter().all(|p| p.len() == points[0].len()) {
+        return Err("All points must be of the same dimension");
+    }
+    let steps: Vec<f64> = points
+        .windows(2)
+        .map(|pair| {
+            let distance = norm(&sub(&pair[1], &pair[0]));
+            match parameterization {
+                Parameterization::Uniform => 1.0,
+                Parameterization::ChordLength => distance,
+                Parameterization::Centripetal => distance.sqrt(),
+            }
+        })
+        .collect();
+    let total: f64 = steps.iter().sum();
+    if total == 0.0 {
+        return Err("The points must not all coincide");
+    }
+    let mut params = vec![0.0];
+    let mut sum = 0.0;
+    for step in &steps[..steps.len() - 1] {
+        sum += step;
+        params.push(sum / total);
+    }
+    params.push(1.0);
+    Ok(params)
+}
+
+// The clamped knot vector on [0, 1] whose interior knots average `degree` consecutive
+// parameters (The NURBS Book, eq. 9.8), one control point per parameter
+pub(crate) fn averaged_knots(params: &[f64], degree: usize) -> Vec<f64> {
+    let n = params.len() - 1;
+    let mut knots = vec![0.0; degree + 1];
+    for j in 1..=n - degree {
+        knots.push(params[j..j + degree].iter().sum::<f64>() / degree as f64);
+    }
+    knots.extend(vec![1.0; degree + 1]);
+    knots
+}
+
+fn check_point_weights(points: &[Vec<f64>], point_weights: &[f64]) -> Result<(), &'static str> {
+    if point_weights.len() != points.len() {
+        return Err("Number of weights and points must be the same");
+    }
+    if point_weights.iter().any(|&w| w < 0.0) {
+        return Err("Weights must be non-negative");
+    }
+    Ok(())
+}
+
+// The clamped knot vector on [0, 1] for approximating data at `params` with
+// `control_points` control points, spread so every span contains parameters
+// (The NURBS Book, eq. 9.68 and 9.69)
+pub(crate) fn spread_knots(params: &[f64], degree: usize, control_points: usize) -> Vec<f64> {
+    let n = control_points - 1;
+    let d = params.len() as f64 / (n - degree + 1) as f64;
+    let mut knots = vec![0.0; degree + 1];
+    for j in 1..=n - degree {
+        let i = (j as f64 * d).floor() as usize;
+        let alpha = j as f64 * d - i as f64;
+        knots.push((1.0 - alpha) * params[i - 1] + alpha * params[i]);
+    }
+    knots.extend(vec![1.0; degree + 1]);
+    knots
+}
+
+// The weighted least squares fit on `knots` with the first and last control point fixed
+// to the first and last point (The NURBS Book, eq. 9.63 to 9.67). The normal equations
+// (N^T W N) P = N^T W R for the inner control points are banded as well.
+fn least_squares(
+    points: &[Vec<f64>],
+    params: &[f64],
+    point_weights: &[f64],
+    degree: usize,
+    knots: &[f64],
+) -> Result<Vec<Vec<f64>>, &'static str> {
+    // index of the last control point
+    let n = knots.len() - degree - 2;
+    let (first, last) = (&points[0], &points[points.len() - 1]);
+    let mut control_points = vec![first.clone()];
+    if n > 1 {
+        let mut matrix = BandMatrix::new(n - 1, degree, degree);
+        let mut rhs = vec![vec![0.0; first.len()]; n - 1];
+        for k in 1..points.len() - 1 {
+            let (start, basis) = collocation_row(degree, knots, params[k], 0);
+            // the residual left after the fixed end points
+            let mut residual = points[k].clone();
+            for (j, &b) in basis.iter().enumerate() {
+                if start + j == 0 {
+                    residual = sub(&residual, &scale(first, b));
+                } else if start + j == n {
+                    residual = sub(&residual, &scale(last, b));
+                }
+            }
+            let w = point_weights[k];
+            for (a, &ba) in basis.iter().enumerate() {
+                let row = start + a;
+                if row == 0 || row == n {
+                    continue;
+                }
+                for (r, x) in rhs[row - 1].iter_mut().zip(&residual) {
+                    *r += w * ba * x;
+                }
+                for (b, &bb) in basis.iter().enumerate() {
+                    let column = start + b;
+                    if column != 0 && column != n {
+                        matrix.add(row - 1, column - 1, w * ba * bb);
+                    }
+                }
+            }
+        }
+        control_points.extend(matrix.solve(rhs)?);
+    }
+    control_points.push(last.clone());
+    Ok(control_points)
+}
+
+// Solves for the control points of the curve on `knots` through `points` at `params`,
+// with optional derivatives at both ends
+pub(crate) fn interpolation_points(
+    points: &[Vec<f64>],
+    params: &[f64],
+    degree: usize,
+    knots: &[f64],
+    start_tangent: Option<&[f64]>,
+    end_tangent: Option<&[f64]>,
+) -> Result<Vec<Vec<f64>>, &'static str> {
+    let m = points.len() - 1;
+    let mut rows = Vec::with_capacity(knots.len() - degree - 1);
+    let mut rhs = Vec::with_capacity(knots.len() - degree - 1);
+    for (k, (point, &t)) in points.iter().zip(params).enumerate() {
+        rows.push(collocation_row(degree, knots, t, 0));
+        rhs.push(point.clone());
+        if k == 0 {
+            if let Some(d) = start_tangent {
+                rows.push(collocation_row(degree, knots, t, 1));
+                rhs.push(d.to_vec());
+            }
+        }
+        if k + 1 == m {
+            if let Some(d) = end_tangent {
+                rows.push(collocation_row(degree, knots, 1.0, 1));
+                rhs.push(d.to_vec());
+            }
+        }
+    }
+    BandMatrix::from_rows(&rows).solve(rhs)
+}
+
+// The row of the collocation matrix for the `order`-th derivative at `t`,
+// as the index of the first control point and the nonzero basis values
+pub(crate) fn collocation_row(
+    degree: usize,
+    knots: &[f64],
+    t: f64,
+    order: usize,
+) -> (usize, Vec<f64>) {
+    let span = find_span(degree, knots, t);
+    let mut ders = bspline_basis_derivatives(span, degree, knots, t, order);
+    (span - degree, ders.swap_remove(order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parameterization;
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{norm, sub};
+
+    fn data() -> Vec<Vec<f64>> {
+        vec![
+            vec![0.0, 0.0],
+            vec![3.0, 4.0],
+            vec![-1.0, 4.0],
+            vec![-4.0, 0.0],
+            vec![-4.0, -3.0],
+        ]
+    }
+
+    #[test]
+    fn test_interpolate() {
+        // the example of The NURBS Book, section 9.2.1, with chord length parameters
+        // 0, 5/17, 9/17, 14/17, 1 and the knots 0, 0, 0, 0, 28/51, 1, 1, 1, 1
+        let points = data();
+        let curve = NURBSCurve::interpolate(&points, 3, Parameterization::ChordLength).unwrap();
+        assert!((curve.knots()[4] - 28.0 / 51.0).abs() < 1e-12);
+        for (point, t) in points.iter().zip([0.0, 5.0, 9.0, 14.0, 17.0]) {
+            assert!(norm(&sub(&curve.eval(t / 17.0).unwrap(), point)) < 1e-12);
+        }
+        for parameterization in [Parameterization::Uniform, Parameterization::Centripetal] {
+            let curve = NURBSCurve::interpolate(&points, 2, parameterization).unwrap();
+            assert_eq!(curve.control_points().len(), points.len());
+            for point in &points {
+                assert!(curve.closest_point(point).unwrap().distance < 1e-10);
+            }
+        }
+        // two points and degree 1 give the line segment
+        let line = NURBSCurve::interpolate(&points[..2], 1, Parameterization::Uniform).unwrap();
+        assert_eq!(line.control_points(), &points[..2]);
+        assert!(NURBSCurve::interpolate(&points[..3], 3, Parameterization::Uniform).is_err());
+        let twice = vec![points[0].clone(), points[0].clone()];
+        assert!(NURBSCurve::interpolate(&twice, 1, Parameterization::ChordLength).is_err());
+    }
+
+    #[test]
+    fn test_interpolate_with_tangents() {
+        let points = data();
+        let (start, end) = ([0.0, 10.0], [-5.0, -10.0]);
+        let curve = NURBSCurve::interpolate_with_tangents(
+            &points,
+            3,
+            Parameterization::Centripetal,
+            Some(&start),
+            Some(&end),
+        )
+        .unwrap();
+        assert_eq!(curve.control_points().len(), points.len() + 2);
+        assert!(norm(&sub(&curve.tangent(0.0).unwrap(), &start)) < 1e-10);
+        assert!(norm(&sub(&curve.tangent(1.0).unwrap(), &end)) < 1e-10);
+        for point in &points {
+            assert!(curve.closest_point(point).unwrap().distance < 1e-10);
+        }
+        // cubic Hermite interpolation between two points
+        let hermite = NURBSCurve::interpolate_with_tangents(
+            &points[..2],
+            3,
+            Parameterization::ChordLength,
+            Some(&[3.0, 0.0]),
+            Some(&[0.0, 3.0]),
+        )
+        .unwrap();
+        assert_eq!(hermite.control_points()[1], vec![1.0, 0.0]);
+        assert_eq!(hermite.control_points()[2], vec![3.0, 3.0]);
+    }
+
+    // heights z = sin(x) * cos(y) on an irregular grid, with a little noise for `noise` > 0
+    fn height_grid(nx: usize, ny: usize, noise: f64) -> Vec<Vec<Vec<f64>>> {
+        (0..nx)
+            .map(|i| {
+                let x = 3.0 * (i as f64 / (nx - 1) as f64).powf(1.2);
+                (0..ny)
+                    .map(|j| {
+                        let y = 2.0 * j as f64 / (ny - 1) as f64;
+                        let jitter = noise * (((i * 31 + j * 17) % 11) as f64 / 11.0 - 0.5);
+                        vec![x, y, x.sin() * y.cos() + jitter]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_interpolate_surface() {
+        let grid = height_grid(7, 5, 0.0);
+        let surface = NURBSSurface::interpolate(&grid, 3, 2).unwrap();
+        assert_eq!(surface.control_points().len(), 7);
+        assert_eq!(surface.control_points()[0].len(), 5);
+        for point in grid.iter().flatten() {
+            assert!(surface.closest_point(point).unwrap().distance < 1e-10);
+        }
+        assert!(NURBSSurface::interpolate(&grid, 3, 5).is_err());
+        assert!(NURBSSurface::interpolate(&grid[..1], 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_approximate_surface() {
+        let grid = height_grid(30, 20, 0.01);
+        let surface = NURBSSurface::approximate(&grid, 3, 3, 8, 6).unwrap();
+        assert_eq!(surface.control_points().len(), 8);
+        assert_eq!(surface.control_points()[0].len(), 6);
+        // the corners are kept and all points are close
+        assert_eq!(surface.eval(1.0, 0.0).unwrap(), grid[29][0]);
+        let projections = surface.closest_points(&grid.concat()).unwrap();
+        assert!(projections.iter().all(|p| p.distance < 0.02));
+        assert!(NURBSSurface::approximate(&grid, 3, 3, 31, 6).is_err());
+    }
+
+    // noisy samples of a sine wave
+    fn samples(count: usize) -> Vec<Vec<f64>> {
+        (0..count)
+            .map(|i| {
+                let x = i as f64 / (count - 1) as f64 * 6.0;
+                let noise = 0.01 * ((i * 7919) % 13) as f64 / 13.0 - 0.005;
+                vec![x, x.sin() + noise]
+            })
+            .collect()
+    }
+
+    fn max_deviation(curve: &NURBSCurve, points: &[Vec<f64>]) -> f64 {
+        points
+            .iter()
+            .map(|p| curve.closest_point(p).unwrap().distance)
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_approximate() {
+        let points = samples(200);
+        let coarse = NURBSCurve::approximate(&points, 3, 5).unwrap();
+        let fine = NURBSCurve::approximate(&points, 3, 12).unwrap();
+        assert_eq!(fine.control_points().len(), 12);
+        // the end points are kept
+        assert_eq!(fine.eval(0.0).unwrap(), points[0]);
+        assert_eq!(fine.eval(1.0).unwrap(), points[199]);
+        assert!(max_deviation(&fine, &points) < 0.01);
+        assert!(max_deviation(&fine, &points) < max_deviation(&coarse, &points));
+        // a heavy weight pulls the curve towards its point
+        let mut weights = vec![1.0; 200];
+        let light = coarse.closest_point(&points[60]).unwrap().distance;
+        weights[60] = 1000.0;
+        let heavy = NURBSCurve::approximate_weighted(&points, &weights, 3, 5).unwrap();
+        assert!(heavy.closest_point(&points[60]).unwrap().distance < 0.1 * light);
+        // points on a line are reproduced
+        let line: Vec<Vec<f64>> = (0..10).map(|i| vec![i as f64, 2.0 * i as f64]).collect();
+        let fit = NURBSCurve::approximate(&line, 2, 4).unwrap();
+        assert!(max_deviation(&fit, &line) < 1e-12);
+        assert!(NURBSCurve::approximate(&points, 3, 3).is_err());
+        assert!(NURBSCurve::approximate_weighted(&points, &[1.0], 3, 5).is_err());
+    }
+
+    #[test]
+    fn test_approximate_within() {
+        let points = samples(500);
+        for tolerance in [0.1, 0.01] {
+            let curve = NURBSCurve::approximate_within(&points, 3, tolerance).unwrap();
+            assert!(curve.control_points().len() < 50);
+            assert!(max_deviation(&curve, &points) <= tolerance);
+        }
+        // below the noise level the spans run out of points to split between
+        let points = samples(60);
+        assert!(NURBSCurve::approximate_within(&points, 3, 1e-4).is_err());
+        assert!(NURBSCurve::approximate_within(&points, 3, 0.0).is_err());
+    }
+}
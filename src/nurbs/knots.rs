@@ -0,0 +1,721 @@
+// nurbs/knots.rs
+// Knot insertion and refinement change the representation of a curve or surface
+// (knots, control points and weights) while keeping its geometry exactly.
+// Knot removal reverses them as far as the geometry allows within a tolerance.
+use crate::find_span;
+use crate::nurbs::{NURBSCurve, NURBSSurface, Rows};
+use crate::vector::{norm, sub};
+
+// Number of times `t` occurs in `knots`
+pub fn knot_multiplicity(knots: &[f64], t: f64) -> usize {
+    knots.iter().filter(|&&k| k == t).count()
+}
+
+// The distinct knot values within the domain of a knot vector
+pub(crate) fn breakpoints(degree: usize, knots: &[f64]) -> Vec<f64> {
+    let (start, end) = (knots[degree], knots[knots.len() - degree - 1]);
+    let mut breaks: Vec<f64> = vec![start];
+    for &k in knots {
+        if k > breaks[breaks.len() - 1] && k <= end {
+            breaks.push(k);
+        }
+    }
+    breaks
+}
+
+// `count` evenly spaced parameters on every span between the breakpoints, plus the end
+pub(crate) fn span_samples(breaks: &[f64], count: usize) -> Vec<f64> {
+    let mut samples = Vec::new();
+    for span in breaks.windows(2) {
+        for i in 0..count {
+            samples.push(span[0] + (span[1] - span[0]) * i as f64 / count as f64);
+        }
+    }
+    samples.push(breaks[breaks.len() - 1]);
+    samples
+}
+
+// Checks that the sorted `new_knots` can be inserted into `knots`: they have to lie within
+// the domain and no knot may end up with a multiplicity greater than the degree
+pub(crate) fn check_refinement(
+    degree: usize,
+    knots: &[f64],
+    new_knots: &[f64],
+) -> Result<(), &'static str> {
+    let (start, end) = (knots[degree], knots[knots.len() - degree - 1]);
+    if new_knots.windows(2).any(|w| w[0] > w[1]) {
+        return Err("Knots must be non-decreasing");
+    }
+    for &x in new_knots {
+        if x < start || x > end {
+            return Err("The knot to insert is out of range of the knot values");
+        }
+        if knot_multiplicity(knots, x) + knot_multiplicity(new_knots, x) > degree {
+            return Err("Knot multiplicity must not exceed the degree");
+        }
+    }
+    Ok(())
+}
+
+// Inserts the sorted `new_knots` into `knots` and computes the new control points from
+// the homogeneous `points_w` (Piegl & Tiller, The NURBS Book, algorithm A5.4).
+// Each inserted knot replaces degree control points by degree + 1 points on the lines
+// between them (Boehm's algorithm), all knots are inserted in one sweep from the back.
+pub(crate) fn refine_homogeneous(
+    degree: usize,
+    knots: &[f64],
+    points_w: &[Vec<f64>],
+    new_knots: &[f64],
+) -> (Vec<f64>, Vec<Vec<f64>>) {
+    if new_knots.is_empty() {
+        return (knots.to_vec(), points_w.to_vec());
+    }
+    let p = degree;
+    let n = points_w.len() - 1;
+    let m = n + p + 1;
+    let r = new_knots.len() - 1;
+    let a = find_span(p, knots, new_knots[0]);
+    let b = find_span(p, knots, new_knots[r]) + 1;
+
+    let mut q = vec![Vec::new(); n + r + 2];
+    let mut new = vec![0.0; m + r + 2];
+    q[..=a - p].clone_from_slice(&points_w[..=a - p]);
+    for j in b - 1..=n {
+        q[j + r + 1] = points_w[j].clone();
+    }
+    new[..=a].copy_from_slice(&knots[..=a]);
+    for j in b + p..=m {
+        new[j + r + 1] = knots[j];
+    }
+
+    let mut i = b + p - 1;
+    let mut k = b + p + r;
+    for &x in new_knots.iter().rev() {
+        while x <= knots[i] && i > a {
+            q[k - p - 1] = points_w[i - p - 1].clone();
+            new[k] = knots[i];
+            k -= 1;
+            i -= 1;
+        }
+        q[k - p - 1] = q[k - p].clone();
+        for l in 1..=p {
+            let ind = k - p + l;
+            let alpha = new[k + l] - x;
+            if alpha.abs() == 0.0 {
+                q[ind - 1] = q[ind].clone();
+            } else {
+                let alpha = alpha / (new[k + l] - knots[i + l - p]);
+                q[ind - 1] = q[ind - 1]
+                    .iter()
+                    .zip(&q[ind])
+                    .map(|(a, b)| alpha * a + (1.0 - alpha) * b)
+                    .collect();
+            }
+        }
+        new[k] = x;
+        k -= 1;
+    }
+    (new, q)
+}
+
+// Tries to remove the interior knot `t` up to `num` times from `knots` with the homogeneous
+// control points `points_w` (Piegl & Tiller, The NURBS Book, algorithm A5.8).
+// Each removal solves for the new control points from both ends of the affected range and
+// is accepted only if the two solutions meet within `tolerance` in homogeneous space.
+// Returns the number of removed copies together with the new knots and control points.
+pub(crate) fn remove_knot_homogeneous(
+    degree: usize,
+    knots: &[f64],
+    points_w: &[Vec<f64>],
+    t: f64,
+    num: usize,
+    tolerance: f64,
+) -> (usize, Vec<f64>, Vec<Vec<f64>>) {
+    let p = degree;
+    let n = points_w.len() - 1;
+    let m = n + p + 1;
+    let s = knot_multiplicity(knots, t);
+    // index of the last occurrence of the knot
+    let r = match knots.iter().rposition(|&k| k == t) {
+        Some(r) => r,
+        None => return (0, knots.to_vec(), points_w.to_vec()),
+    };
+    let mut knots = knots.to_vec();
+    let mut pw = points_w.to_vec();
+    let ord = p + 1;
+    let fout = (2 * r - s - p) / 2;
+    let mut first = r - p;
+    let mut last = r - s;
+    let mut temp = vec![Vec::new(); 2 * p + 1];
+    let blend = |a: f64, x: &[f64], y: &[f64]| -> Vec<f64> {
+        x.iter()
+            .zip(y)
+            .map(|(x, y)| a * x + (1.0 - a) * y)
+            .collect()
+    };
+
+    let mut removed = 0;
+    while removed < num.min(s) {
+        let off = first - 1;
+        temp[0] = pw[off].clone();
+        temp[last + 1 - off] = pw[last + 1].clone();
+        let (mut i, mut j) = (first, last);
+        let (mut ii, mut jj) = (1, last - off);
+        while j > i + removed {
+            let alpha_i = (t - knots[i]) / (knots[i + ord + removed] - knots[i]);
+            let alpha_j = (t - knots[j - removed]) / (knots[j + ord] - knots[j - removed]);
+            temp[ii] = pw[i]
+                .iter()
+                .zip(&temp[ii - 1])
+                .map(|(p, q)| (p - (1.0 - alpha_i) * q) / alpha_i)
+                .collect();
+            temp[jj] = pw[j]
+                .iter()
+                .zip(&temp[jj + 1])
+                .map(|(p, q)| (p - alpha_j * q) / (1.0 - alpha_j))
+                .collect();
+            i += 1;
+            ii += 1;
+            j -= 1;
+            jj -= 1;
+        }
+        let deviation = if j < i + removed {
+            norm(&sub(&temp[ii - 1], &temp[jj + 1]))
+        } else {
+            let alpha_i = (t - knots[i]) / (knots[i + ord + removed] - knots[i]);
+            norm(&sub(
+                &pw[i],
+                &blend(alpha_i, &temp[ii + removed + 1], &temp[ii - 1]),
+            ))
+        };
+        // removal must not produce non-positive weights either
+        let weights_valid = temp[1..last + 1 - off]
+            .iter()
+            .all(|pw| pw.last().is_none_or(|&w| w > 0.0));
+        if deviation > tolerance || !weights_valid {
+            break;
+        }
+        let (mut i, mut j) = (first, last);
+        while j > i + removed {
+            pw[i] = temp[i - off].clone();
+            pw[j] = temp[j - off].clone();
+            i += 1;
+            j -= 1;
+        }
+        removed += 1;
+        if first == 1 {
+            break;
+        }
+        first -= 1;
+        last += 1;
+    }
+    if removed == 0 {
+        return (0, knots, pw);
+    }
+
+    for k in r + 1..=m {
+        knots[k - removed] = knots[k];
+    }
+    knots.truncate(m + 1 - removed);
+    // the removal leaves the control points to drop in the middle of the affected range
+    let mut j = fout;
+    let mut i = j;
+    for k in 1..removed {
+        if k % 2 == 1 {
+            i += 1;
+        } else {
+            j -= 1;
+        }
+    }
+    for k in i + 1..=n {
+        pw[j] = pw[k].clone();
+        j += 1;
+    }
+    pw.truncate(n + 1 - removed);
+    (removed, knots, pw)
+}
+
+// Converts a geometric tolerance into one for homogeneous control points, which bounds
+// the deviation of the curve by `tolerance` (The NURBS Book, eq. 5.30)
+pub(crate) fn homogeneous_tolerance(tolerance: f64, weights: &[f64], points: &[&Vec<f64>]) -> f64 {
+    let w_min = weights.iter().cloned().fold(f64::INFINITY, f64::min);
+    let p_max = points.iter().map(|p| norm(p)).fold(0.0, f64::max);
+    tolerance * w_min / (1.0 + p_max)
+}
+
+// Checks that `t` is an interior knot of `knots`, which is what knot removal needs
+fn check_interior_knot(degree: usize, knots: &[f64], t: f64) -> Result<(), &'static str> {
+    let (start, end) = (knots[degree], knots[knots.len() - degree - 1]);
+    if t <= start || t >= end || knot_multiplicity(knots, t) == 0 {
+        return Err("The knot to remove is not an interior knot");
+    }
+    Ok(())
+}
+
+// The share of `tolerance` for each of the up to `times` removals of the knot `t`,
+// which keeps the accumulated deviation of all of them within `tolerance`
+fn split_tolerance(knots: &[f64], t: f64, times: usize, tolerance: f64) -> f64 {
+    tolerance / times.min(knot_multiplicity(knots, t)).max(1) as f64
+}
+
+// Refines every row of control points sharing `knots` with the same `new_knots`
+pub(crate) fn refine_rows(
+    degree: usize,
+    knots: &[f64],
+    rows: &[Vec<Vec<f64>>],
+    new_knots: &[f64],
+) -> (Vec<f64>, Rows) {
+    let mut refined_knots = knots.to_vec();
+    let refined = rows
+        .iter()
+        .map(|points_w| {
+            let (knots, points_w) = refine_homogeneous(degree, knots, points_w, new_knots);
+            refined_knots = knots;
+            points_w
+        })
+        .collect();
+    (refined_knots, refined)
+}
+
+// Removes `t` up to `num` times from every row of control points sharing `knots`.
+// A copy is only removed if it can be removed from all rows within `tolerance`,
+// so the rows keep a common knot vector. The tolerance bounds every removal on its own,
+// callers split their budget between the removals.
+pub(crate) fn remove_knot_rows(
+    degree: usize,
+    knots: &[f64],
+    rows: &[Vec<Vec<f64>>],
+    t: f64,
+    num: usize,
+    tolerance: f64,
+) -> (usize, Vec<f64>, Rows) {
+    let mut knots = knots.to_vec();
+    let mut rows = rows.to_vec();
+    let mut removed = 0;
+    while removed < num && knot_multiplicity(&knots, t) > 0 {
+        let mut new_knots = Vec::new();
+        let mut new_rows = Vec::with_capacity(rows.len());
+        for points_w in &rows {
+            let (count, knots, points_w) =
+                remove_knot_homogeneous(degree, &knots, points_w, t, 1, tolerance);
+            if count == 0 {
+                return (removed, knots, rows);
+            }
+            new_knots = knots;
+            new_rows.push(points_w);
+        }
+        knots = new_knots;
+        rows = new_rows;
+        removed += 1;
+    }
+    (removed, knots, rows)
+}
+
+// Makes the knot vector clamped, so that the first and last of the degree + 1 equal knots
+// delimit the domain, by inserting its ends up to multiplicity degree and dropping the
+// knots and control points outside of the domain. Works on every row of control points
+// sharing `knots`.
+pub(crate) fn clamp_rows(degree: usize, knots: &[f64], rows: &[Vec<Vec<f64>>]) -> (Vec<f64>, Rows) {
+    let p = degree;
+    let (start, end) = (knots[p], knots[knots.len() - p - 1]);
+    let mut new_knots = vec![start; p.saturating_sub(knot_multiplicity(knots, start))];
+    new_knots.extend(vec![end; p.saturating_sub(knot_multiplicity(knots, end))]);
+    let (knots, rows) = refine_rows(p, knots, rows, &new_knots);
+    let first = knots.iter().rposition(|&k| k == start).unwrap_or(p) - p;
+    let last = knots
+        .iter()
+        .position(|&k| k == end)
+        .unwrap_or(knots.len() - p - 1);
+    let mut clamped = knots[first..=last + p].to_vec();
+    clamped[0] = start;
+    clamped[last + p - first] = end;
+    let rows = rows.iter().map(|row| row[first..last].to_vec()).collect();
+    (clamped, rows)
+}
+
+// Refines the knot vector until every interior knot has multiplicity degree, which splits
+// the curve into Bézier segments. The segment k of a row is given by the control points
+// k * degree ..= (k + 1) * degree. Works on every row of control points sharing `knots`.
+pub(crate) fn decompose_rows(
+    degree: usize,
+    knots: &[f64],
+    rows: &[Vec<Vec<f64>>],
+) -> (Vec<f64>, Rows) {
+    let (knots, rows) = clamp_rows(degree, knots, rows);
+    let (start, end) = (knots[0], knots[knots.len() - 1]);
+    let mut new_knots = Vec::new();
+    for (i, &k) in knots.iter().enumerate() {
+        if k > start && k < end && knots[i - 1] < k {
+            let s = knot_multiplicity(&knots, k);
+            new_knots.extend(vec![k; degree.saturating_sub(s)]);
+        }
+    }
+    refine_rows(degree, &knots, &rows, &new_knots)
+}
+
+impl NURBSCurve {
+    // Inserts the knot `t` `times` times (Boehm's knot insertion).
+    // The resulting curve has the same shape and parameterization,
+    // the multiplicity of `t` must not exceed the degree afterwards.
+    pub fn insert_knot(&self, t: f64, times: usize) -> Result<NURBSCurve, &'static str> {
+        self.refine_knots(&vec![t; times])
+    }
+
+    // Inserts all of the non-decreasing `new_knots` at once (knot refinement)
+    pub fn refine_knots(&self, new_knots: &[f64]) -> Result<NURBSCurve, &'static str> {
+        check_refinement(self.degree(), self.knots(), new_knots)?;
+        let (knots, points_w) = refine_homogeneous(
+            self.degree(),
+            self.knots(),
+            &self.homogeneous_points(),
+            new_knots,
+        );
+        NURBSCurve::from_homogeneous(self.degree(), knots, &points_w)
+    }
+
+    // Removes the interior knot `t` up to `times` times, as long as every removal moves the
+    // curve by no more than `tolerance`. Returns the new curve and how many copies were
+    // actually removed, which is zero if the knot cannot be removed within the tolerance.
+    // The tolerance is split evenly between the copies to remove, so the result stays
+    // within it of the original curve however many copies are removed.
+    pub fn remove_knot(
+        &self,
+        t: f64,
+        times: usize,
+        tolerance: f64,
+    ) -> Result<(NURBSCurve, usize), &'static str> {
+        check_interior_knot(self.degree(), self.knots(), t)?;
+        let tolerance =
+            self.homogeneous_tolerance(split_tolerance(self.knots(), t, times, tolerance));
+        let (removed, knots, points_w) = remove_knot_homogeneous(
+            self.degree(),
+            self.knots(),
+            &self.homogeneous_points(),
+            t,
+            times,
+            tolerance,
+        );
+        Ok((
+            NURBSCurve::from_homogeneous(self.degree(), knots, &points_w)?,
+            removed,
+        ))
+    }
+
+    // Converts a geometric tolerance into one for the homogeneous control points
+    pub(crate) fn homogeneous_tolerance(&self, tolerance: f64) -> f64 {
+        let points: Vec<&Vec<f64>> = self.control_points().iter().collect();
+        homogeneous_tolerance(tolerance, self.weights(), &points)
+    }
+}
+
+impl NURBSSurface {
+    // Inserts the knot `u` `times` times into the u direction, see NURBSCurve::insert_knot()
+    pub fn insert_knot_u(&self, u: f64, times: usize) -> Result<NURBSSurface, &'static str> {
+        self.refine_knots_u(&vec![u; times])
+    }
+
+    // Inserts the knot `v` `times` times into the v direction, see NURBSCurve::insert_knot()
+    pub fn insert_knot_v(&self, v: f64, times: usize) -> Result<NURBSSurface, &'static str> {
+        self.refine_knots_v(&vec![v; times])
+    }
+
+    // Inserts all of the non-decreasing `new_knots` into the u direction at once
+    pub fn refine_knots_u(&self, new_knots: &[f64]) -> Result<NURBSSurface, &'static str> {
+        check_refinement(self.degree_u(), self.knots_u(), new_knots)?;
+        let (knots_u, rows) =
+            refine_rows(self.degree_u(), self.knots_u(), &self.rows_u(), new_knots);
+        NURBSSurface::from_rows_u(
+            self.degree_u(),
+            self.degree_v(),
+            knots_u,
+            self.knots_v().to_vec(),
+            &rows,
+        )
+    }
+
+    // Inserts all of the non-decreasing `new_knots` into the v direction at once
+    pub fn refine_knots_v(&self, new_knots: &[f64]) -> Result<NURBSSurface, &'static str> {
+        check_refinement(self.degree_v(), self.knots_v(), new_knots)?;
+        let (knots_v, net) = refine_rows(
+            self.degree_v(),
+            self.knots_v(),
+            &self.homogeneous_net(),
+            new_knots,
+        );
+        NURBSSurface::from_homogeneous_net(
+            self.degree_u(),
+            self.degree_v(),
+            self.knots_u().to_vec(),
+            knots_v,
+            &net,
+        )
+    }
+
+    // Removes the interior knot `u` of the u direction up to `times` times, see
+    // NURBSCurve::remove_knot(). A copy is only removed if it can be removed from every
+    // row of control points along u within the tolerance.
+    pub fn remove_knot_u(
+        &self,
+        u: f64,
+        times: usize,
+        tolerance: f64,
+    ) -> Result<(NURBSSurface, usize), &'static str> {
+        check_interior_knot(self.degree_u(), self.knots_u(), u)?;
+        let tolerance =
+            self.homogeneous_tolerance(split_tolerance(self.knots_u(), u, times, tolerance));
+        let (removed, knots_u, rows) = remove_knot_rows(
+            self.degree_u(),
+            self.knots_u(),
+            &self.rows_u(),
+            u,
+            times,
+            tolerance,
+        );
+        let surface = NURBSSurface::from_rows_u(
+            self.degree_u(),
+            self.degree_v(),
+            knots_u,
+            self.knots_v().to_vec(),
+            &rows,
+        )?;
+        Ok((surface, removed))
+    }
+
+    // Removes the interior knot `v` of the v direction up to `times` times, see remove_knot_u()
+    pub fn remove_knot_v(
+        &self,
+        v: f64,
+        times: usize,
+        tolerance: f64,
+    ) -> Result<(NURBSSurface, usize), &'static str> {
+        check_interior_knot(self.degree_v(), self.knots_v(), v)?;
+        let tolerance =
+            self.homogeneous_tolerance(split_tolerance(self.knots_v(), v, times, tolerance));
+        let (removed, knots_v, net) = remove_knot_rows(
+            self.degree_v(),
+            self.knots_v(),
+            &self.homogeneous_net(),
+            v,
+            times,
+            tolerance,
+        );
+        let surface = NURBSSurface::from_homogeneous_net(
+            self.degree_u(),
+            self.degree_v(),
+            self.knots_u().to_vec(),
+            knots_v,
+            &net,
+        )?;
+        Ok((surface, removed))
+    }
+
+    // Converts a geometric tolerance into one for the homogeneous control net
+    pub(crate) fn homogeneous_tolerance(&self, tolerance: f64) -> f64 {
+        let weights: Vec<f64> = self.weights().iter().flatten().cloned().collect();
+        let points: Vec<&Vec<f64>> = self.control_points().iter().flatten().collect();
+        homogeneous_tolerance(tolerance, &weights, &points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{norm, sub};
+
+    fn curve() -> NURBSCurve {
+        NURBSCurve::new(
+            3,
+            vec![0.0, 0.0, 0.0, 0.0, 0.4, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0, 0.5, 2.0, 1.0, 1.0],
+            vec![
+                vec![0.0, 0.0, 0.0],
+                vec![1.0, 2.0, 0.0],
+                vec![2.0, 2.0, 1.0],
+                vec![3.0, 0.0, 1.0],
+                vec![4.0, 1.0, 0.0],
+            ],
+        )
+        .unwrap()
+    }
+
+    fn surface() -> NURBSSurface {
+        let knots = vec![0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0];
+        let weights = vec![
+            vec![1.0, 1.0, 2.0, 1.0],
+            vec![0.5, 1.0, 1.0, 1.0],
+            vec![1.0, 3.0, 1.0, 1.0],
+            vec![1.0, 1.0, 1.0, 0.5],
+        ];
+        let control_points = (0..4)
+            .map(|i| {
+                (0..4)
+                    .map(|j| vec![i as f64, j as f64, ((i * j) % 3) as f64])
+                    .collect()
+            })
+            .collect();
+        NURBSSurface::new(2, 2, knots.clone(), knots, weights, control_points).unwrap()
+    }
+
+    fn assert_same_curve(a: &NURBSCurve, b: &NURBSCurve) {
+        for i in 0..=50 {
+            let t = i as f64 / 50.0;
+            assert!(norm(&sub(&a.eval(t).unwrap(), &b.eval(t).unwrap())) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_insert_knot() {
+        let curve = curve();
+        let inserted = curve.insert_knot(0.7, 2).unwrap();
+        assert_eq!(inserted.knots().len(), curve.knots().len() + 2);
+        assert_eq!(inserted.control_points().len(), 7);
+        assert_eq!(
+            inserted.knots(),
+            &[0.0, 0.0, 0.0, 0.0, 0.4, 0.7, 0.7, 1.0, 1.0, 1.0, 1.0]
+        );
+        assert_same_curve(&curve, &inserted);
+        // at an existing knot
+        assert_same_curve(&curve, &curve.insert_knot(0.4, 1).unwrap());
+        // multiplicity would exceed the degree
+        assert!(curve.insert_knot(0.4, 3).is_err());
+        assert!(curve.insert_knot(1.0, 1).is_err());
+        assert!(curve.insert_knot(1.5, 1).is_err());
+    }
+
+    #[test]
+    fn test_refine_knots() {
+        let curve = curve();
+        let refined = curve.refine_knots(&[0.1, 0.2, 0.4, 0.5, 0.5, 0.9]).unwrap();
+        assert_eq!(refined.control_points().len(), 11);
+        assert_same_curve(&curve, &refined);
+        assert!(curve.refine_knots(&[0.5, 0.2]).is_err());
+    }
+
+    #[test]
+    fn test_clamp_and_decompose() {
+        use super::{clamp_rows, decompose_rows};
+        // unclamped uniform cubic
+        let knots: Vec<f64> = (0..9).map(|k| k as f64).collect();
+        let unclamped = NURBSCurve::new(
+            3,
+            knots.clone(),
+            vec![1.0, 2.0, 1.0, 0.5, 1.0],
+            vec![
+                vec![0.0, 0.0],
+                vec![1.0, 3.0],
+                vec![2.0, -1.0],
+                vec![4.0, 2.0],
+                vec![5.0, 0.0],
+            ],
+        )
+        .unwrap();
+        let rows = vec![unclamped.homogeneous_points()];
+        let (clamped_knots, clamped_rows) = clamp_rows(3, &knots, &rows);
+        assert_eq!(
+            clamped_knots,
+            vec![3.0, 3.0, 3.0, 3.0, 4.0, 5.0, 5.0, 5.0, 5.0]
+        );
+        let clamped = NURBSCurve::from_homogeneous(3, clamped_knots, &clamped_rows[0]).unwrap();
+        let (bezier_knots, bezier_rows) = decompose_rows(3, &knots, &rows);
+        assert_eq!(bezier_knots.len(), 11);
+        assert_eq!(bezier_rows[0].len(), 7);
+        let bezier = NURBSCurve::from_homogeneous(3, bezier_knots, &bezier_rows[0]).unwrap();
+        for i in 0..=20 {
+            let t = 3.0 + i as f64 / 10.0;
+            let point = unclamped.eval(t).unwrap();
+            assert!(norm(&sub(&point, &clamped.eval(t).unwrap())) < 1e-12);
+            assert!(norm(&sub(&point, &bezier.eval(t).unwrap())) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_remove_knot() {
+        // knots that were inserted can be removed again without changing the curve
+        let curve = curve();
+        let inserted = curve.insert_knot(0.7, 2).unwrap();
+        let (removed, count) = inserted.remove_knot(0.7, 3, 1e-9).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(removed.knots(), curve.knots());
+        assert_same_curve(&curve, &removed);
+
+        // after moving a control point slightly, only a loose enough tolerance removes the knot
+        let mut points = inserted.control_points().to_vec();
+        points[4][2] += 1e-3;
+        let moved = NURBSCurve::new(
+            3,
+            inserted.knots().to_vec(),
+            inserted.weights().to_vec(),
+            points,
+        )
+        .unwrap();
+        let (unchanged, count) = moved.remove_knot(0.7, 1, 1e-6).unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(unchanged, moved);
+        let (reduced, count) = moved.remove_knot(0.7, 1, 5e-2).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(reduced.control_points().len(), 6);
+        for i in 0..=50 {
+            let t = i as f64 / 50.0;
+            let deviation = norm(&sub(&moved.eval(t).unwrap(), &reduced.eval(t).unwrap()));
+            assert!(deviation < 5e-2);
+        }
+
+        // removing both copies keeps the accumulated deviation within the tolerance
+        let tolerance = 5e-2;
+        let (reduced, count) = moved.remove_knot(0.7, 2, tolerance).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(reduced.knots(), curve.knots());
+        let deviation = (0..=200)
+            .map(|i| {
+                let t = i as f64 / 200.0;
+                norm(&sub(&moved.eval(t).unwrap(), &reduced.eval(t).unwrap()))
+            })
+            .fold(0.0, f64::max);
+        assert!(deviation > 0.0 && deviation <= tolerance);
+
+        assert!(curve.remove_knot(0.5, 1, 1.0).is_err());
+        assert!(curve.remove_knot(0.0, 1, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_refine_surface() {
+        let surface = surface();
+        let refined_u = surface.refine_knots_u(&[0.25, 0.5, 0.75]).unwrap();
+        let refined_v = surface.insert_knot_v(0.3, 2).unwrap();
+        assert_eq!(refined_u.control_points().len(), 7);
+        assert_eq!(refined_v.control_points()[0].len(), 6);
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (u, v) = (i as f64 / 10.0, j as f64 / 10.0);
+                let point = surface.eval(u, v).unwrap();
+                assert!(norm(&sub(&point, &refined_u.eval(u, v).unwrap())) < 1e-12);
+                assert!(norm(&sub(&point, &refined_v.eval(u, v).unwrap())) < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_knot_surface() {
+        let surface = surface();
+        let refined = surface
+            .insert_knot_u(0.25, 2)
+            .unwrap()
+            .insert_knot_v(0.8, 1)
+            .unwrap();
+        let (removed_u, count) = refined.remove_knot_u(0.25, 2, 1e-9).unwrap();
+        assert_eq!(count, 2);
+        let (removed, count) = removed_u.remove_knot_v(0.8, 1, 1e-9).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(removed.knots_u(), surface.knots_u());
+        assert_eq!(removed.knots_v(), surface.knots_v());
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (u, v) = (i as f64 / 10.0, j as f64 / 10.0);
+                let point = surface.eval(u, v).unwrap();
+                assert!(norm(&sub(&point, &removed.eval(u, v).unwrap())) < 1e-9);
+            }
+        }
+        // the knot of the original surface is needed for its shape
+        assert_eq!(surface.remove_knot_u(0.5, 1, 1e-6).unwrap().1, 0);
+    }
+}
@@ -0,0 +1,216 @@
+// nurbs/mesh.rs
+// Indexed triangle meshes and writers for common mesh file formats
+use std::io::{self, Write};
+
+use crate::vector::{cross, normalize, sub};
+
+// An indexed triangle mesh in 3D. positions, normals and uvs hold one entry per vertex,
+// the (u, v) surface parameters of a tessellated surface serve as texture coordinates.
+// Triangles are wound counterclockwise seen from the side the normals point to.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vec<f64>>,
+    pub normals: Vec<Vec<f64>>,
+    pub uvs: Vec<[f64; 2]>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    // The unit normal of a triangle from its winding, zero for degenerate triangles
+    pub fn face_normal(&self, triangle: &[usize; 3]) -> Vec<f64> {
+        let [a, b, c] = triangle.map(|i| &self.positions[i]);
+        normalize(&cross(&sub(b, a), &sub(c, a))).unwrap_or_else(|| vec![0.0; 3])
+    }
+
+    // Wavefront OBJ with positions (v), texture coordinates (vt) and normals (vn), the
+    // latter two only if the mesh has them
+    pub fn write_obj<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for p in &self.positions {
+            writeln!(out, "v {} {} {}", p[0], p[1], p[2])?;
+        }
+        for uv in &self.uvs {
+            writeln!(out, "vt {} {}", uv[0], uv[1])?;
+        }
+        for n in &self.normals {
+            writeln!(out, "vn {} {} {}", n[0], n[1], n[2])?;
+        }
+        // OBJ indices start at 1
+        let corner = |i: usize| match (self.uvs.is_empty(), self.normals.is_empty()) {
+            (true, true) => format!("{i}"),
+            (false, true) => format!("{i}/{i}"),
+            (true, false) => format!("{i}//{i}"),
+            (false, false) => format!("{i}/{i}/{i}"),
+        };
+        for t in &self.triangles {
+            let [a, b, c] = t.map(|i| corner(i + 1));
+            writeln!(out, "f {a} {b} {c}")?;
+        }
+        Ok(())
+    }
+
+    // ASCII STL, STL stores facets with their face normal only
+    pub fn write_stl_ascii<W: Write>(&self, out: &mut W, name: &str) -> io::Result<()> {
+        writeln!(out, "solid {name}")?;
+        for t in &self.triangles {
+            let n = self.face_normal(t);
+            writeln!(out, "  facet normal {} {} {}", n[0], n[1], n[2])?;
+            writeln!(out, "    outer loop")?;
+            for &i in t {
+                let p = &self.positions[i];
+                writeln!(out, "      vertex {} {} {}", p[0], p[1], p[2])?;
+            }
+            writeln!(out, "    endloop")?;
+            writeln!(out, "  endfacet")?;
+        }
+        writeln!(out, "endsolid {name}")
+    }
+
+    // Binary STL, an 80 byte header, the number of facets and 50 bytes per facet
+    // with little endian f32 values
+    pub fn write_stl_binary<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut header = [0u8; 80];
+        let title = b"binary STL written by bsfun";
+        header[..title.len()].copy_from_slice(title);
+        out.write_all(&header)?;
+        out.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+        for t in &self.triangles {
+            let n = self.face_normal(t);
+            let corners = t.iter().map(|&i| &self.positions[i]);
+            for value in n.iter().chain(corners.flatten().take(9)) {
+                out.write_all(&(*value as f32).to_le_bytes())?;
+            }
+            // attribute byte count
+            out.write_all(&[0, 0])?;
+        }
+        Ok(())
+    }
+
+    // ASCII PLY with positions, normals and texture coordinates per vertex, the latter
+    // two only if the mesh has them
+    pub fn write_ply<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let (has_normals, has_uvs) = (!self.normals.is_empty(), !self.uvs.is_empty());
+        writeln!(out, "ply")?;
+        writeln!(out, "format ascii 1.0")?;
+        writeln!(out, "element vertex {}", self.positions.len())?;
+        let mut properties = vec!["x", "y", "z"];
+        if has_normals {
+            properties.extend(["nx", "ny", "nz"]);
+        }
+        if has_uvs {
+            properties.extend(["s", "t"]);
+        }
+        for property in properties {
+            writeln!(out, "property double {property}")?;
+        }
+        writeln!(out, "element face {}", self.triangles.len())?;
+        writeln!(out, "property list uchar uint vertex_indices")?;
+        writeln!(out, "end_header")?;
+        for (i, p) in self.positions.iter().enumerate() {
+            write!(out, "{} {} {}", p[0], p[1], p[2])?;
+            if has_normals {
+                let n = &self.normals[i];
+                write!(out, " {} {} {}", n[0], n[1], n[2])?;
+            }
+            if has_uvs {
+                write!(out, " {} {}", self.uvs[i][0], self.uvs[i][1])?;
+            }
+            writeln!(out)?;
+        }
+        for [a, b, c] in &self.triangles {
+            writeln!(out, "3 {a} {b} {c}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mesh;
+
+    // the unit square in the xy plane made of two triangles
+    fn square() -> Mesh {
+        Mesh {
+            positions: vec![
+                vec![0.0, 0.0, 0.0],
+                vec![1.0, 0.0, 0.0],
+                vec![1.0, 1.0, 0.0],
+                vec![0.0, 1.0, 0.0],
+            ],
+            normals: vec![vec![0.0, 0.0, 1.0]; 4],
+            uvs: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+        }
+    }
+
+    #[test]
+    fn test_mesh_writers() {
+        let mesh = square();
+        assert_eq!(mesh.face_normal(&[0, 1, 2]), vec![0.0, 0.0, 1.0]);
+
+        let mut obj = Vec::new();
+        mesh.write_obj(&mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert!(obj.starts_with("v 0 0 0\nv 1 0 0\n"));
+        assert!(obj.contains("vt 1 1\n") && obj.contains("vn 0 0 1\n"));
+        assert!(obj.ends_with("f 1/1/1 3/3/3 4/4/4\n"));
+        // faces only refer to the attributes that are there
+        for (uvs, normals, face) in [
+            (false, false, "f 1 3 4\n"),
+            (true, false, "f 1/1 3/3 4/4\n"),
+            (false, true, "f 1//1 3//3 4//4\n"),
+        ] {
+            let mut partial = mesh.clone();
+            if !uvs {
+                partial.uvs.clear();
+            }
+            if !normals {
+                partial.normals.clear();
+            }
+            let mut obj = Vec::new();
+            partial.write_obj(&mut obj).unwrap();
+            let obj = String::from_utf8(obj).unwrap();
+            assert!(obj.ends_with(face));
+            assert_eq!(obj.contains("vt "), uvs);
+            assert_eq!(obj.contains("vn "), normals);
+
+            // PLY declares and writes the same properties for every vertex
+            let mut ply = Vec::new();
+            partial.write_ply(&mut ply).unwrap();
+            let ply = String::from_utf8(ply).unwrap();
+            assert_eq!(ply.contains("property double nx\n"), normals);
+            assert_eq!(ply.contains("property double s\n"), uvs);
+            let properties = ply.matches("property double").count();
+            assert_eq!(properties, 3 + 3 * normals as usize + 2 * uvs as usize);
+            let body = ply.split("end_header\n").nth(1).unwrap();
+            let vertices: Vec<&str> = body.lines().take(4).collect();
+            assert!(vertices
+                .iter()
+                .all(|line| line.split(' ').count() == properties));
+            assert_eq!(
+                vertices[1].split(' ').take(3).collect::<Vec<_>>(),
+                ["1", "0", "0"]
+            );
+        }
+
+        let mut stl = Vec::new();
+        mesh.write_stl_ascii(&mut stl, "square").unwrap();
+        let stl = String::from_utf8(stl).unwrap();
+        assert_eq!(stl.matches("facet normal 0 0 1").count(), 2);
+        assert!(stl.starts_with("solid square\n") && stl.ends_with("endsolid square\n"));
+
+        let mut stl = Vec::new();
+        mesh.write_stl_binary(&mut stl).unwrap();
+        assert_eq!(stl.len(), 84 + 2 * 50);
+        assert_eq!(stl[80..84], 2u32.to_le_bytes());
+        // the z component of the first normal and the x coordinate of the second vertex
+        assert_eq!(stl[92..96], 1f32.to_le_bytes());
+        assert_eq!(stl[108..112], 1f32.to_le_bytes());
+
+        let mut ply = Vec::new();
+        mesh.write_ply(&mut ply).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply.contains("element vertex 4\n") && ply.contains("element face 2\n"));
+        assert!(ply.contains("end_header\n0 0 0 0 0 1 0 0\n"));
+        assert!(ply.ends_with("3 0 2 3\n"));
+    }
+}
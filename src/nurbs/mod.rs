@@ -1,7 +1,35 @@
 // nurbs/mod.rs
+pub mod arc_length;
+pub mod bezier;
+pub mod conics;
+pub mod construction;
+pub mod coons;
+pub mod curve_geometry;
+pub mod degree;
+pub mod fitting;
+pub mod knots;
+pub mod mesh;
 pub mod nurbs_curve;
 pub mod nurbs_surface;
+pub mod projection;
+pub mod scattered;
+pub mod split;
+pub mod surface_geometry;
+pub mod sweep;
+pub mod tessellate;
+
+// rows of homogeneous control points (w * P, w) that share one knot vector,
+// a curve has one row and a surface one per control point along the other direction
+pub(crate) type Rows = Vec<Vec<Vec<f64>>>;
 
 // re-export to crate::nurbs::{...}
+pub use arc_length::EquidistantPoints;
+pub use bezier::{BezierPatch, BezierSegment};
+pub use curve_geometry::Frame;
+pub use fitting::Parameterization;
+pub use knots::knot_multiplicity;
+pub use mesh::Mesh;
 pub use nurbs_curve::NURBSCurve;
 pub use nurbs_surface::NURBSSurface;
+pub use projection::{CurveProjection, SurfaceProjection};
+pub use surface_geometry::{CurvatureSample, FundamentalForms, PrincipalCurvatures};
@@ -1,5 +1,8 @@
-use crate::rational_bspline_basis_curve;
+use crate::nurbs::knots::breakpoints;
+use crate::vector::{cartesian, homogeneous, normalize};
+use crate::{nurbs_curve_derivatives, nurbs_curve_point};
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct NURBSCurve {
     degree: usize,
     knots: Vec<f64>,
@@ -37,7 +40,10 @@ impl NURBSCurve {
             }
         }
         // the implementation uses Vec<Vec<>> so points could potentially be of different dimension
-        if !control_points.iter().all(|ref v| v.len() == control_points[0].len()) {
+        if !control_points
+            .iter()
+            .all(|v| v.len() == control_points[0].len())
+        {
             return Err("All control points must be of the same dimension");
         }
 
@@ -50,21 +56,91 @@ impl NURBSCurve {
         })
     }
 
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    pub fn knots(&self) -> &[f64] {
+        &self.knots
+    }
+
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    pub fn control_points(&self) -> &[Vec<f64>] {
+        &self.control_points
+    }
+
+    // The distinct knot values within the domain, the curve is a single
+    // rational polynomial between two consecutive breakpoints
+    pub fn breakpoints(&self) -> Vec<f64> {
+        breakpoints(self.degree, &self.knots)
+    }
+
+    // The control points in homogeneous coordinates (w * P, w)
+    pub(crate) fn homogeneous_points(&self) -> Vec<Vec<f64>> {
+        homogeneous(&self.control_points, &self.weights)
+    }
+
+    // Builds a curve from control points in homogeneous coordinates
+    pub(crate) fn from_homogeneous(
+        degree: usize,
+        knots: Vec<f64>,
+        points_w: &[Vec<f64>],
+    ) -> Result<Self, &'static str> {
+        let (control_points, weights) = cartesian(points_w);
+        NURBSCurve::new(degree, knots, weights, control_points)
+    }
+
+    // The parameter range the curve is defined on, between the first and last `degree` knots
+    pub fn domain(&self) -> (f64, f64) {
+        (
+            self.knots[self.degree],
+            self.knots[self.knots.len() - self.degree - 1],
+        )
+    }
+
     pub fn eval(&self, t: f64) -> Result<Vec<f64>, &'static str> {
-        if t < *self.knots.first().unwrap_or(&0.0) || t > *self.knots.last().unwrap_or(&0.0) {
+        let (start, end) = self.domain();
+        if t < start || t > end {
             return Err("The parameter t is out of range of the knot values");
         }
-        let n = self.control_points.len();
-        let d = self.control_points[0].len();
-        let mut result = vec![0.0; d];
-        for i in 0..n {
-            let basis = rational_bspline_basis_curve(i, self.degree, &self.knots, &self.weights, t);
-            for j in 0..d {
-                // fill every axis of the result vector
-                result[j] += basis * self.control_points[i][j];
-            }
+        Ok(nurbs_curve_point(
+            t,
+            &self.control_points,
+            &self.weights,
+            &self.knots,
+            self.degree,
+        ))
+    }
+
+    // Calculates the point at `t` and its derivatives with respect to `t` up to `order`.
+    // The k-th entry of the result is the k-th derivative, the first one is the point.
+    pub fn derivatives(&self, t: f64, order: usize) -> Result<Vec<Vec<f64>>, &'static str> {
+        let (start, end) = self.domain();
+        if t < start || t > end {
+            return Err("The parameter t is out of range of the knot values");
         }
-        Ok(result)
+        Ok(nurbs_curve_derivatives(
+            t,
+            &self.control_points,
+            &self.weights,
+            &self.knots,
+            self.degree,
+            order,
+        ))
+    }
+
+    // The tangent (velocity) vector dC/dt at `t`, its length depends on the parameterization
+    pub fn tangent(&self, t: f64) -> Result<Vec<f64>, &'static str> {
+        let mut ders = self.derivatives(t, 1)?;
+        Ok(ders.remove(1))
+    }
+
+    // The tangent at `t` normalized to unit length
+    pub fn unit_tangent(&self, t: f64) -> Result<Vec<f64>, &'static str> {
+        normalize(&self.tangent(t)?).ok_or("The tangent vanishes at t")
     }
 }
 
@@ -90,4 +166,21 @@ mod tests {
             println!("Curve at t = {}: {:?}", t, point);
         }
     }
+
+    #[test]
+    fn test_nurbs_curve_tangent() {
+        // the quadratic bezier from (0, 0) over (0.5, 0.5) to (1, 0) has the
+        // velocity 2 * (P1 - P0) at its start and 2 * (P2 - P1) at its end
+        let knots = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let weights = vec![1.0, 1.0, 1.0];
+        let control_points = vec![vec![0.0, 0.0], vec![0.5, 0.5], vec![1.0, 0.0]];
+        let curve = NURBSCurve::new(2, knots, weights, control_points).unwrap();
+
+        assert_eq!(curve.tangent(0.0).unwrap(), vec![1.0, 1.0]);
+        assert_eq!(curve.tangent(1.0).unwrap(), vec![1.0, -1.0]);
+        assert_eq!(curve.derivatives(0.5, 2).unwrap()[2], vec![0.0, -2.0]);
+        let unit = curve.unit_tangent(0.5).unwrap();
+        assert!((unit[0] - 1.0).abs() < 1e-12 && unit[1].abs() < 1e-12);
+        assert!(curve.tangent(1.5).is_err());
+    }
 }
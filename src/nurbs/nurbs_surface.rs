@@ -1,4 +1,7 @@
-use crate::rational_bspline_basis_surface;
+use crate::nurbs::knots::breakpoints;
+use crate::vector::{cartesian, homogeneous};
+use crate::{nurbs_surface_derivatives, nurbs_surface_point};
+#[derive(Debug, Clone, PartialEq)]
 pub struct NURBSSurface {
     degree_u: usize,
     degree_v: usize,
@@ -9,7 +12,14 @@ pub struct NURBSSurface {
 }
 
 impl NURBSSurface {
-    pub fn new(degree_u: usize, degree_v: usize, knots_u: Vec<f64>, knots_v: Vec<f64>, weights: Vec<Vec<f64>>, control_points: Vec<Vec<Vec<f64>>>) -> Result<Self, &'static str> {
+    pub fn new(
+        degree_u: usize,
+        degree_v: usize,
+        knots_u: Vec<f64>,
+        knots_v: Vec<f64>,
+        weights: Vec<Vec<f64>>,
+        control_points: Vec<Vec<Vec<f64>>>,
+    ) -> Result<Self, &'static str> {
         if degree_u == 0 || degree_v == 0 {
             return Err("Degree must be greater than 0");
         }
@@ -49,9 +59,9 @@ impl NURBSSurface {
         }
 
         // Check that the weights are non-negative
-        for i in 0..weights.len() {
-            for j in 0..weights[0].len() {
-                if weights[i][j] < 0.0 {
+        for row in &weights {
+            for &w in row {
+                if w < 0.0 {
                     return Err("Weights must be non-negative");
                 }
             }
@@ -66,43 +76,168 @@ impl NURBSSurface {
             }
         }
 
-        Ok(NURBSSurface { 
-            degree_u, 
-            degree_v, 
-            knots_u, 
-            knots_v, 
-            weights, 
-            control_points 
+        Ok(NURBSSurface {
+            degree_u,
+            degree_v,
+            knots_u,
+            knots_v,
+            weights,
+            control_points,
         })
     }
 
-    pub fn eval(&self, u: f64, v: f64) -> Result<Vec<f64>, &'static str> {
-        if u < *self.knots_u.first().unwrap_or(&0.0) || u > *self.knots_u.last().unwrap_or(&0.0) {
+    pub fn degree_u(&self) -> usize {
+        self.degree_u
+    }
+
+    pub fn degree_v(&self) -> usize {
+        self.degree_v
+    }
+
+    pub fn knots_u(&self) -> &[f64] {
+        &self.knots_u
+    }
+
+    pub fn knots_v(&self) -> &[f64] {
+        &self.knots_v
+    }
+
+    pub fn weights(&self) -> &[Vec<f64>] {
+        &self.weights
+    }
+
+    pub fn control_points(&self) -> &[Vec<Vec<f64>>] {
+        &self.control_points
+    }
+
+    // The parameter range of u the surface is defined on
+    pub fn domain_u(&self) -> (f64, f64) {
+        (
+            self.knots_u[self.degree_u],
+            self.knots_u[self.knots_u.len() - self.degree_u - 1],
+        )
+    }
+
+    // The parameter range of v the surface is defined on
+    pub fn domain_v(&self) -> (f64, f64) {
+        (
+            self.knots_v[self.degree_v],
+            self.knots_v[self.knots_v.len() - self.degree_v - 1],
+        )
+    }
+
+    // The distinct knot values within the domain of u, see NURBSCurve::breakpoints()
+    pub fn breakpoints_u(&self) -> Vec<f64> {
+        breakpoints(self.degree_u, &self.knots_u)
+    }
+
+    // The distinct knot values within the domain of v, see NURBSCurve::breakpoints()
+    pub fn breakpoints_v(&self) -> Vec<f64> {
+        breakpoints(self.degree_v, &self.knots_v)
+    }
+
+    // The control net in homogeneous coordinates (w * P, w), indexed like the control points
+    pub(crate) fn homogeneous_net(&self) -> Vec<Vec<Vec<f64>>> {
+        self.control_points
+            .iter()
+            .zip(&self.weights)
+            .map(|(row, weights)| homogeneous(row, weights))
+            .collect()
+    }
+
+    // Builds a surface from a control net in homogeneous coordinates
+    pub(crate) fn from_homogeneous_net(
+        degree_u: usize,
+        degree_v: usize,
+        knots_u: Vec<f64>,
+        knots_v: Vec<f64>,
+        net: &[Vec<Vec<f64>>],
+    ) -> Result<Self, &'static str> {
+        let (control_points, weights) = net.iter().map(|row| cartesian(row)).unzip();
+        NURBSSurface::new(
+            degree_u,
+            degree_v,
+            knots_u,
+            knots_v,
+            weights,
+            control_points,
+        )
+    }
+
+    // The homogeneous control net as rows along u, that is net[..][j] for every j.
+    // The rows along v are the homogeneous net itself.
+    pub(crate) fn rows_u(&self) -> Vec<Vec<Vec<f64>>> {
+        transpose(&self.homogeneous_net())
+    }
+
+    // Builds a surface from homogeneous rows along u, see rows_u()
+    pub(crate) fn from_rows_u(
+        degree_u: usize,
+        degree_v: usize,
+        knots_u: Vec<f64>,
+        knots_v: Vec<f64>,
+        rows: &[Vec<Vec<f64>>],
+    ) -> Result<Self, &'static str> {
+        NURBSSurface::from_homogeneous_net(degree_u, degree_v, knots_u, knots_v, &transpose(rows))
+    }
+
+    fn check_params(&self, u: f64, v: f64) -> Result<(), &'static str> {
+        let (u_start, u_end) = self.domain_u();
+        let (v_start, v_end) = self.domain_v();
+        if u < u_start || u > u_end {
             return Err("Parameter u is out of the knot spans bounds");
         }
-        if v < *self.knots_v.first().unwrap_or(&0.0) || v > *self.knots_v.last().unwrap_or(&0.0) {
+        if v < v_start || v > v_end {
             return Err("Parameter v is out of the knot spans bounds");
         }
+        Ok(())
+    }
 
-        let n = self.control_points.len();
-        let m = self.control_points[0].len();
-        let d = self.control_points[0][0].len();
-
-        let mut result = vec![0.0; d];
-
-        for i in 0..n {
-            for j in 0..m {
-                let basis = rational_bspline_basis_surface(i, j, self.degree_u, self.degree_v, &self.knots_u, &self.knots_v, &self.weights, u, v);
-                for k in 0..d {
-                    result[k] += basis * self.control_points[i][j][k];
-                }
-            }
-        }
+    pub fn eval(&self, u: f64, v: f64) -> Result<Vec<f64>, &'static str> {
+        self.check_params(u, v)?;
+        Ok(nurbs_surface_point(
+            &self.control_points,
+            &self.weights,
+            &self.knots_u,
+            &self.knots_v,
+            self.degree_u,
+            self.degree_v,
+            u,
+            v,
+        ))
+    }
 
-        Ok(result)
+    // Calculates the point at (u, v) and its partial derivatives up to a total `order`.
+    // ders[k][l] is the derivative k times with respect to u and l times with respect to v
+    // for k + l <= order, so ders[0][0] is the point, ders[1][0] is Su and ders[1][1] is Suv.
+    pub fn derivatives(
+        &self,
+        u: f64,
+        v: f64,
+        order: usize,
+    ) -> Result<Vec<Vec<Vec<f64>>>, &'static str> {
+        self.check_params(u, v)?;
+        Ok(nurbs_surface_derivatives(
+            &self.control_points,
+            &self.weights,
+            &self.knots_u,
+            &self.knots_v,
+            self.degree_u,
+            self.degree_v,
+            u,
+            v,
+            order,
+        ))
     }
 }
 
+// Swaps the two indices of a control net, which turns the rows along v
+// into the rows along u and back
+pub(crate) fn transpose(net: &[Vec<Vec<f64>>]) -> Vec<Vec<Vec<f64>>> {
+    (0..net[0].len())
+        .map(|j| net.iter().map(|row| row[j].clone()).collect())
+        .collect()
+}
 
 #[cfg(test)]
 mod tests {
@@ -116,10 +251,7 @@ mod tests {
         let knots_u = vec![0.0, 0.0, 1.0, 1.0];
         let knots_v = vec![0.0, 0.0, 1.0, 1.0];
 
-        let weights = vec![
-            vec![1.0, 1.0],
-            vec![1.0, 1.0],
-        ];
+        let weights = vec![vec![1.0, 1.0], vec![1.0, 1.0]];
 
         let control_points = vec![
             vec![
@@ -132,12 +264,44 @@ mod tests {
             ],
         ];
 
-        let surface = NURBSSurface::new(degree_u, degree_v, knots_u, knots_v, weights, control_points).unwrap();
+        let surface = NURBSSurface::new(
+            degree_u,
+            degree_v,
+            knots_u,
+            knots_v,
+            weights,
+            control_points,
+        )
+        .unwrap();
 
         let u = 0.5;
         let v = 0.75;
 
         let result = surface.eval(u, v).unwrap();
-        assert_eq!(result, vec![0.75, 0.5, 0.0]);  // Since we're on the xy plane, z should be 0
+        assert_eq!(result, vec![0.75, 0.5, 0.0]); // Since we're on the xy plane, z should be 0
+    }
+
+    #[test]
+    fn test_nurbs_surface_derivatives() {
+        // the saddle z = u * v over the unit square
+        let surface = NURBSSurface::new(
+            1,
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+            vec![
+                vec![vec![0.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]],
+                vec![vec![1.0, 0.0, 0.0], vec![1.0, 1.0, 1.0]],
+            ],
+        )
+        .unwrap();
+        let ders = surface.derivatives(0.25, 0.5, 2).unwrap();
+        assert_eq!(ders[0][0], vec![0.25, 0.5, 0.125]);
+        assert_eq!(ders[1][0], vec![1.0, 0.0, 0.5]);
+        assert_eq!(ders[0][1], vec![0.0, 1.0, 0.25]);
+        assert_eq!(ders[1][1], vec![0.0, 0.0, 1.0]);
+        assert_eq!(ders[2][0], vec![0.0, 0.0, 0.0]);
+        assert!(surface.derivatives(0.5, 1.5, 1).is_err());
     }
 }
@@ -0,0 +1,468 @@
+// nurbs/projection.rs
+// Point inversion: the closest point on a curve or surface to a given point
+use crate::nurbs::knots::span_samples;
+use crate::nurbs::{NURBSCurve, NURBSSurface};
+use crate::vector::{dot, norm, sub};
+
+// tolerance for coincident points and for the zero cosine test of Newton's method
+const POINT_TOLERANCE: f64 = 1e-12;
+const COSINE_TOLERANCE: f64 = 1e-12;
+const MAX_ITERATIONS: usize = 50;
+const MAX_HALVINGS: usize = 20;
+
+// a surface point (u, v, S(u, v)) of the seed grid
+type Sample = (f64, f64, Vec<f64>);
+
+// The result of projecting a point onto a curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurveProjection {
+    pub t: f64,
+    pub point: Vec<f64>,
+    pub distance: f64,
+}
+
+// The result of projecting a point onto a surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceProjection {
+    pub u: f64,
+    pub v: f64,
+    pub point: Vec<f64>,
+    pub distance: f64,
+}
+
+impl NURBSCurve {
+    // Whether the curve ends where it starts
+    pub fn is_closed(&self) -> bool {
+        let (start, end) = self.domain();
+        match (self.eval(start), self.eval(end)) {
+            (Ok(a), Ok(b)) => norm(&sub(&a, &b)) < POINT_TOLERANCE,
+            _ => false,
+        }
+    }
+
+    // Finds the point on the curve closest to `p` (point inversion).
+    // The curve is sampled on every knot span for a starting parameter, which is then
+    // refined with Newton's method on f(t) = C'(t) . (C(t) - p) = 0 (The NURBS Book,
+    // section 6.1). Newton steps that leave the domain are clamped to its ends, or wrap
+    // around for closed curves, and steps that would move away from p are halved.
+    pub fn closest_point(&self, p: &[f64]) -> Result<CurveProjection, &'static str> {
+        if p.len() != self.control_points()[0].len() {
+            return Err("The point must have the same dimension as the control points");
+        }
+        let (start, end) = self.domain();
+        let closed = self.is_closed();
+
+        // coarse samples, more on higher degree spans since they can bend more
+        let samples = 2 * (self.degree() + 1);
+        let mut best = (start, f64::INFINITY);
+        for span in self.breakpoints().windows(2) {
+            for i in 0..=samples {
+                let t = span[0] + (span[1] - span[0]) * i as f64 / samples as f64;
+                let distance = norm(&sub(&self.eval(t)?, p));
+                if distance < best.1 {
+                    best = (t, distance);
+                }
+            }
+        }
+
+        let mut t = best.0;
+        for _ in 0..MAX_ITERATIONS {
+            let ders = self.derivatives(t, 2)?;
+            let diff = sub(&ders[0], p);
+            let distance = norm(&diff);
+            if distance < POINT_TOLERANCE {
+                break;
+            }
+            let f = dot(&ders[1], &diff);
+            let speed = norm(&ders[1]);
+            if speed < f64::EPSILON || f.abs() / (speed * distance) < COSINE_TOLERANCE {
+                break;
+            }
+            let df = dot(&ders[2], &diff) + speed * speed;
+            // away from a minimum f' can be negative, then step downhill along the gradient
+            let mut step = if df > 0.0 {
+                -f / df
+            } else {
+                -f / (speed * speed)
+            };
+            let mut accepted = None;
+            for _ in 0..MAX_HALVINGS {
+                let mut next = t + step;
+                if next < start || next > end {
+                    next = if closed {
+                        start + (next - start).rem_euclid(end - start)
+                    } else {
+                        next.clamp(start, end)
+                    };
+                }
+                if norm(&sub(&self.eval(next)?, p)) <= distance {
+                    accepted = Some(next);
+                    break;
+                }
+                // backtrack if the full step moves away from p
+                step *= 0.5;
+            }
+            match accepted {
+                Some(next) if ((next - t) * speed).abs() >= POINT_TOLERANCE => t = next,
+                Some(next) => {
+                    t = next;
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        let point = self.eval(t)?;
+        let distance = norm(&sub(&point, p));
+        Ok(CurveProjection { t, point, distance })
+    }
+}
+
+impl NURBSSurface {
+    // Whether the boundaries at both ends of u coincide, checked at the breakpoints
+    // and span midpoints of v
+    pub fn is_closed_u(&self) -> bool {
+        let (start, end) = self.domain_u();
+        boundary_samples(&self.breakpoints_v()).iter().all(|&v| {
+            match (self.eval(start, v), self.eval(end, v)) {
+                (Ok(a), Ok(b)) => norm(&sub(&a, &b)) < POINT_TOLERANCE,
+                _ => false,
+            }
+        })
+    }
+
+    // Whether the boundaries at both ends of v coincide, see is_closed_u()
+    pub fn is_closed_v(&self) -> bool {
+        let (start, end) = self.domain_v();
+        boundary_samples(&self.breakpoints_u()).iter().all(|&u| {
+            match (self.eval(u, start), self.eval(u, end)) {
+                (Ok(a), Ok(b)) => norm(&sub(&a, &b)) < POINT_TOLERANCE,
+                _ => false,
+            }
+        })
+    }
+
+    // Finds the point on the surface closest to `p` (point inversion).
+    // The starting parameters are the closest of a grid with degree + 1 samples per knot
+    // span in both directions, refined with Newton's method on the two equations
+    // f = Su . (S - p) = 0 and g = Sv . (S - p) = 0 (The NURBS Book, section 6.1).
+    // Steps that leave the domain are clamped to its boundary, or wrap around in closed
+    // directions, and steps that would move away from p are halved. Where a parameter
+    // sits on the boundary and the distance decreases outwards, it is held fixed and
+    // Newton's method continues in the other parameter alone (projected Newton).
+    pub fn closest_point(&self, p: &[f64]) -> Result<SurfaceProjection, &'static str> {
+        let grid = self.seed_grid()?;
+        let closed = [self.is_closed_u(), self.is_closed_v()];
+        self.project(&grid, closed, p)
+    }
+
+    // Projects many points at once, the seed grid and the closedness of the surface
+    // are only determined a single time
+    pub fn closest_points(
+        &self,
+        points: &[Vec<f64>],
+    ) -> Result<Vec<SurfaceProjection>, &'static str> {
+        let grid = self.seed_grid()?;
+        let closed = [self.is_closed_u(), self.is_closed_v()];
+        points
+            .iter()
+            .map(|p| self.project(&grid, closed, p))
+            .collect()
+    }
+
+    // Samples (u, v, S(u, v)) for the starting parameters of the projection
+    fn seed_grid(&self) -> Result<Vec<Sample>, &'static str> {
+        let us = span_samples(&self.breakpoints_u(), self.degree_u() + 1);
+        let vs = span_samples(&self.breakpoints_v(), self.degree_v() + 1);
+        let mut grid = Vec::with_capacity(us.len() * vs.len());
+        for &u in &us {
+            for &v in &vs {
+                grid.push((u, v, self.eval(u, v)?));
+            }
+        }
+        Ok(grid)
+    }
+
+    fn project(
+        &self,
+        grid: &[Sample],
+        closed: [bool; 2],
+        p: &[f64],
+    ) -> Result<SurfaceProjection, &'static str> {
+        if p.len() != self.control_points()[0][0].len() {
+            return Err("The point must have the same dimension as the control points");
+        }
+        let domains = [self.domain_u(), self.domain_v()];
+        let (mut u, mut v, _) = grid
+            .iter()
+            .map(|(u, v, point)| (*u, *v, norm(&sub(point, p))))
+            .fold((domains[0].0, domains[1].0, f64::INFINITY), |best, c| {
+                if c.2 < best.2 {
+                    c
+                } else {
+                    best
+                }
+            });
+
+        for _ in 0..MAX_ITERATIONS {
+            let ders = self.derivatives(u, v, 2)?;
+            let diff = sub(&ders[0][0], p);
+            let distance = norm(&diff);
+            if distance < POINT_TOLERANCE {
+                break;
+            }
+            let (su, sv) = (&ders[1][0], &ders[0][1]);
+            let (f, g) = (dot(su, &diff), dot(sv, &diff));
+            let (len_u, len_v) = (norm(su), norm(sv));
+            // a parameter on an open boundary is held there while the distance
+            // decreases outwards, f and g being the gradient of |S - p|^2 / 2
+            let held = |k: usize, param: f64, gradient: f64| {
+                let (start, end) = domains[k];
+                !closed[k]
+                    && ((param <= start && gradient > 0.0) || (param >= end && gradient < 0.0))
+            };
+            let (held_u, held_v) = (held(0, u, f), held(1, v, g));
+            let zero_u =
+                held_u || len_u < f64::EPSILON || f.abs() / (len_u * distance) < COSINE_TOLERANCE;
+            let zero_v =
+                held_v || len_v < f64::EPSILON || g.abs() / (len_v * distance) < COSINE_TOLERANCE;
+            if zero_u && zero_v {
+                break;
+            }
+            // J = [[a, b], [b, c]] is the jacobian of (f, g)
+            let a = len_u * len_u + dot(&ders[2][0], &diff);
+            let b = dot(su, sv) + dot(&ders[1][1], &diff);
+            let c = len_v * len_v + dot(&ders[0][2], &diff);
+            let det = a * c - b * b;
+            // away from a minimum J need not be positive definite, then step downhill
+            // along the gradient scaled by the tangent lengths
+            let newton_u = || {
+                if a > 0.0 {
+                    -f / a
+                } else {
+                    -f / (len_u * len_u).max(f64::EPSILON)
+                }
+            };
+            let newton_v = || {
+                if c > 0.0 {
+                    -g / c
+                } else {
+                    -g / (len_v * len_v).max(f64::EPSILON)
+                }
+            };
+            let mut step = if held_u {
+                [0.0, newton_v()]
+            } else if held_v {
+                [newton_u(), 0.0]
+            } else if a > 0.0 && det > f64::EPSILON * a * c {
+                [(-f * c + g * b) / det, (-g * a + f * b) / det]
+            } else {
+                [
+                    -f / (len_u * len_u).max(f64::EPSILON),
+                    -g / (len_v * len_v).max(f64::EPSILON),
+                ]
+            };
+            let mut accepted = None;
+            for _ in 0..MAX_HALVINGS {
+                let next = [u + step[0], v + step[1]];
+                let next: Vec<f64> = (0..2)
+                    .map(|k| {
+                        let (start, end) = domains[k];
+                        if next[k] >= start && next[k] <= end {
+                            next[k]
+                        } else if closed[k] {
+                            start + (next[k] - start).rem_euclid(end - start)
+                        } else {
+                            next[k].clamp(start, end)
+                        }
+                    })
+                    .collect();
+                if norm(&sub(&self.eval(next[0], next[1])?, p)) <= distance {
+                    accepted = Some((next[0], next[1]));
+                    break;
+                }
+                // backtrack if the full step moves away from p
+                step = [step[0] * 0.5, step[1] * 0.5];
+            }
+            match accepted {
+                Some((nu, nv)) => {
+                    let moved = norm(&[(nu - u) * len_u, (nv - v) * len_v]);
+                    u = nu;
+                    v = nv;
+                    if moved < POINT_TOLERANCE {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        let point = self.eval(u, v)?;
+        let distance = norm(&sub(&point, p));
+        Ok(SurfaceProjection {
+            u,
+            v,
+            point,
+            distance,
+        })
+    }
+}
+
+// The breakpoints together with the midpoints of the spans between them
+fn boundary_samples(breaks: &[f64]) -> Vec<f64> {
+    span_samples(breaks, 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    // unit circle made of four rational quadratic quarters
+    fn circle() -> NURBSCurve {
+        let w = FRAC_1_SQRT_2;
+        NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0, 4.0],
+            vec![1.0, w, 1.0, w, 1.0, w, 1.0, w, 1.0],
+            vec![
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+                vec![0.0, 1.0],
+                vec![-1.0, 1.0],
+                vec![-1.0, 0.0],
+                vec![-1.0, -1.0],
+                vec![0.0, -1.0],
+                vec![1.0, -1.0],
+                vec![1.0, 0.0],
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_closest_point_circle() {
+        let circle = circle();
+        assert!(circle.is_closed());
+        for &angle in &[0.1_f64, 1.0, 2.5, 3.9, 5.5, 6.2] {
+            let (s, c) = angle.sin_cos();
+            let projection = circle.closest_point(&[3.0 * c, 3.0 * s]).unwrap();
+            assert!((projection.distance - 2.0).abs() < 1e-10);
+            assert!((projection.point[0] - c).abs() < 1e-10);
+            assert!((projection.point[1] - s).abs() < 1e-10);
+        }
+        // a point on the curve projects onto itself
+        let point = circle.eval(2.3).unwrap();
+        let projection = circle.closest_point(&point).unwrap();
+        assert!((projection.t - 2.3).abs() < 1e-9);
+        assert!(projection.distance < 1e-10);
+        assert!(circle.closest_point(&[1.0, 2.0, 3.0]).is_err());
+    }
+
+    #[test]
+    fn test_closest_point_clamped_to_end() {
+        // beyond the end of an open curve the end point is the closest point
+        let curve = NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![1.0, 1.0, 1.0],
+            vec![vec![0.0, 0.0], vec![1.0, 1.0], vec![2.0, 0.0]],
+        )
+        .unwrap();
+        assert!(!curve.is_closed());
+        let projection = curve.closest_point(&[3.0, -1.0]).unwrap();
+        assert_eq!(projection.t, 1.0);
+        assert_eq!(projection.point, vec![2.0, 0.0]);
+        // the apex of the parabola
+        let projection = curve.closest_point(&[1.0, 2.0]).unwrap();
+        assert!((projection.t - 0.5).abs() < 1e-10);
+        assert!((projection.distance - 1.5).abs() < 1e-10);
+    }
+
+    // a bilinear saddle z = x * y over the unit square
+    fn saddle() -> NURBSSurface {
+        NURBSSurface::new(
+            1,
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+            vec![
+                vec![vec![0.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]],
+                vec![vec![1.0, 0.0, 0.0], vec![1.0, 1.0, 1.0]],
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_closest_point_surface() {
+        let surface = saddle();
+        assert!(!surface.is_closed_u() && !surface.is_closed_v());
+        // points on the surface project onto themselves
+        for &(u, v) in &[(0.3, 0.7), (0.55, 0.15), (0.9, 0.9)] {
+            let point = surface.eval(u, v).unwrap();
+            let projection = surface.closest_point(&point).unwrap();
+            assert!((projection.u - u).abs() < 1e-9 && (projection.v - v).abs() < 1e-9);
+            assert!(projection.distance < 1e-10);
+        }
+        // a point off the surface, the difference is perpendicular to both tangents
+        let p = [0.4, 0.6, 0.8];
+        let projection = surface.closest_point(&p).unwrap();
+        let (u, v) = (projection.u, projection.v);
+        let diff: Vec<f64> = projection
+            .point
+            .iter()
+            .zip(&p)
+            .map(|(a, b)| a - b)
+            .collect();
+        assert!((diff[0] + diff[2] * v).abs() < 1e-10);
+        assert!((diff[1] + diff[2] * u).abs() < 1e-10);
+        assert!(surface.closest_point(&[1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn test_closest_point_surface_boundary() {
+        let surface = saddle();
+        // beyond the corner (1, 1, 1) the corner is the closest point
+        let projection = surface.closest_point(&[2.0, 2.0, 1.5]).unwrap();
+        assert_eq!((projection.u, projection.v), (1.0, 1.0));
+        assert_eq!(projection.point, vec![1.0, 1.0, 1.0]);
+        // beyond the edge u = 0 the closest point lies on that edge
+        let projections = surface
+            .closest_points(&[vec![-1.0, 0.5, 0.0], vec![-0.5, 0.25, 0.0]])
+            .unwrap();
+        assert_eq!(projections[0].u, 0.0);
+        assert!((projections[0].v - 0.5).abs() < 1e-10);
+        assert!((projections[0].distance - 1.0).abs() < 1e-10);
+        assert_eq!(projections[1].u, 0.0);
+        assert!((projections[1].v - 0.25).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_closest_point_skewed_boundary() {
+        // the plane S(u, v) = (u + v, v, 0), whose tangents are not perpendicular,
+        // so the unconstrained Newton step leaves the domain sideways
+        let surface = NURBSSurface::new(
+            1,
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+            vec![
+                vec![vec![0.0, 0.0, 0.0], vec![1.0, 1.0, 0.0]],
+                vec![vec![1.0, 0.0, 0.0], vec![2.0, 1.0, 0.0]],
+            ],
+        )
+        .unwrap();
+        let projections = surface
+            .closest_points(&[vec![0.3, -1.0, 0.0], vec![-0.5, 0.9, 0.0]])
+            .unwrap();
+        assert_eq!(projections[0].v, 0.0);
+        assert!((projections[0].u - 0.3).abs() < 1e-10);
+        assert!((projections[0].distance - 1.0).abs() < 1e-10);
+        assert_eq!(projections[1].u, 0.0);
+        assert!((projections[1].v - 0.2).abs() < 1e-10);
+        assert!((projections[1].distance - 0.98_f64.sqrt()).abs() < 1e-10);
+    }
+}
@@ -0,0 +1,444 @@
+// nurbs/scattered.rs
+// Surfaces from scattered data with multilevel B-spline approximation
+// (Lee, Wolberg and Shin, "Scattered Data Interpolation with Multilevel B-Splines", 1997)
+use crate::nurbs::nurbs_surface::transpose;
+use crate::nurbs::NURBSSurface;
+
+// Gauss-Seidel sweeps that relax a correction lattice towards the membrane penalty
+const SMOOTHING_SWEEPS: usize = 50;
+// upper bound for the control points of the finest lattice, fitting a level takes
+// a few hundred bytes per control point
+const MAX_LATTICE_POINTS: usize = 1 << 20;
+
+// A control lattice of uniform cubic B-Splines over m x n cells, phi[i][j] belongs to the
+// lattice point (i - 1, j - 1) so the lattice reaches one cell beyond the domain
+struct Lattice {
+    m: usize,
+    n: usize,
+    phi: Vec<Vec<Vec<f64>>>,
+}
+
+impl NURBSSurface {
+    // Fits a bicubic surface S(u, v) to `values` given at scattered parameters, so that
+    // S(params[k]) is close to values[k]. The domain is the bounding box of the parameters.
+    // Starting from a lattice with about square cells covering the domain, every one of the
+    // `levels` fits the remaining residuals with the B-Spline approximation (BA) of Lee et
+    // al. on a lattice with twice as many cells per direction, and all levels are added up.
+    // More levels follow the data more closely, the finest lattice has 2^(levels - 1)
+    // times the cells of the first one per direction and must not have more than
+    // MAX_LATTICE_POINTS = 2^20 control points, which allows 10 levels on a square domain.
+    // `smoothing` >= 0 weighs a membrane penalty on the differences of neighbouring control
+    // points of every correction against the fit of the data, 0 gives the plain BA. It is
+    // relative to the average data weight of the control points, so it does not depend on
+    // the scale or the density of the data.
+    // The knots are uniform and not clamped, the surface is non-rational.
+    pub fn fit_scattered(
+        params: &[[f64; 2]],
+        values: &[Vec<f64>],
+        levels: usize,
+        smoothing: f64,
+    ) -> Result<NURBSSurface, &'static str> {
+        if params.is_empty() || params.len() != values.len() {
+            return Err("Number of parameters and values must be the same and not zero");
+        }
+        if !values.iter().all(|v| v.len() == values[0].len()) {
+            return Err("All values must be of the same dimension");
+        }
+        if levels == 0 {
+            return Err("The number of levels must be positive");
+        }
+        if smoothing < 0.0 {
+            return Err("The smoothing must be non-negative");
+        }
+        let (u0, u1) = bounds(params.iter().map(|p| p[0]));
+        let (v0, v1) = bounds(params.iter().map(|p| p[1]));
+        if u1 <= u0 || v1 <= v0 {
+            return Err("The parameters must span an area");
+        }
+
+        // about square cells on the first level
+        let aspect = (u1 - u0) / (v1 - v0);
+        let (m, n) = (aspect.round().max(1.0), aspect.recip().round().max(1.0));
+        let scale = 2f64.powi(levels as i32 - 1);
+        if (m * scale + 3.0) * (n * scale + 3.0) > MAX_LATTICE_POINTS as f64 {
+            return Err("The finest lattice would have too many control points");
+        }
+        let mut lattice = Lattice::zero(m as usize, n as usize, values[0].len());
+        let mut residuals = values.to_vec();
+        for level in 0..levels {
+            if level > 0 {
+                lattice = lattice.refine();
+            }
+            let coords: Vec<(f64, f64)> = params
+                .iter()
+                .map(|p| {
+                    (
+                        (p[0] - u0) / (u1 - u0) * lattice.m as f64,
+                        (p[1] - v0) / (v1 - v0) * lattice.n as f64,
+                    )
+                })
+                .collect();
+            let correction =
+                Lattice::approximate(lattice.m, lattice.n, &coords, &residuals, smoothing);
+            for (residual, &(x, y)) in residuals.iter_mut().zip(&coords) {
+                for (r, c) in residual.iter_mut().zip(correction.eval(x, y)) {
+                    *r -= c;
+                }
+            }
+            lattice.add(&correction);
+        }
+
+        let knots = |start: f64, end: f64, cells: usize| -> Vec<f64> {
+            let h = (end - start) / cells as f64;
+            (0..cells + 7)
+                .map(|k| start + (k as f64 - 3.0) * h)
+                .collect()
+        };
+        let weights = vec![vec![1.0; lattice.n + 3]; lattice.m + 3];
+        NURBSSurface::new(
+            3,
+            3,
+            knots(u0, u1, lattice.m),
+            knots(v0, v1, lattice.n),
+            weights,
+            lattice.phi,
+        )
+    }
+
+    // Fits a height field z = f(x, y) to scattered 3D points with fit_scattered(). The
+    // surface is parameterized by x and y themselves, S(x, y) = (x, y, f(x, y)) over the
+    // bounding box of the points, since the control points sit at the Greville abscissae.
+    pub fn fit_height_field(
+        points: &[Vec<f64>],
+        levels: usize,
+        smoothing: f64,
+    ) -> Result<NURBSSurface, &'static str> {
+        if points.iter().any(|p| p.len() != 3) {
+            return Err("The points must be 3D");
+        }
+        let params: Vec<[f64; 2]> = points.iter().map(|p| [p[0], p[1]]).collect();
+        let heights: Vec<Vec<f64>> = points.iter().map(|p| vec![p[2]]).collect();
+        let heights = NURBSSurface::fit_scattered(&params, &heights, levels, smoothing)?;
+
+        // the uniform knots average to the lattice positions
+        let (knots_u, knots_v) = (heights.knots_u(), heights.knots_v());
+        let control_points = heights
+            .control_points()
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let x = (knots_u[i + 1] + knots_u[i + 2] + knots_u[i + 3]) / 3.0;
+                row.iter()
+                    .enumerate()
+                    .map(|(j, z)| {
+                        let y = (knots_v[j + 1] + knots_v[j + 2] + knots_v[j + 3]) / 3.0;
+                        vec![x, y, z[0]]
+                    })
+                    .collect()
+            })
+            .collect();
+        NURBSSurface::new(
+            3,
+            3,
+            knots_u.to_vec(),
+            knots_v.to_vec(),
+            heights.weights().to_vec(),
+            control_points,
+        )
+    }
+}
+
+impl Lattice {
+    fn zero(m: usize, n: usize, dimension: usize) -> Self {
+        Lattice {
+            m,
+            n,
+            phi: vec![vec![vec![0.0; dimension]; n + 3]; m + 3],
+        }
+    }
+
+    // The BA algorithm: every data point at lattice coordinates (x, y) asks the 4 x 4 control
+    // points around it for the values that reproduce it with the least squares change, and
+    // every control point takes the weighted average of the requests it gets.
+    // With smoothing, the control points minimize sum omega (phi - delta / omega)^2 plus
+    // lambda times the squared differences to their four neighbours instead, which is
+    // solved approximately by Gauss-Seidel sweeps starting from the plain BA.
+    fn approximate(
+        m: usize,
+        n: usize,
+        coords: &[(f64, f64)],
+        values: &[Vec<f64>],
+        smoothing: f64,
+    ) -> Self {
+        let dimension = values[0].len();
+        let mut delta = vec![vec![vec![0.0; dimension]; n + 3]; m + 3];
+        let mut omega = vec![vec![0.0; n + 3]; m + 3];
+        for (&(x, y), value) in coords.iter().zip(values) {
+            let (i, s) = cell(x, m);
+            let (j, t) = cell(y, n);
+            let (bs, bt) = (cubic_basis(s), cubic_basis(t));
+            let sum2: f64 = bs
+                .iter()
+                .flat_map(|a| bt.iter().map(move |b| (a * b) * (a * b)))
+                .sum();
+            for (k, a) in bs.iter().enumerate() {
+                for (l, b) in bt.iter().enumerate() {
+                    let w = a * b;
+                    omega[i + k][j + l] += w * w;
+                    for (d, v) in delta[i + k][j + l].iter_mut().zip(value) {
+                        *d += w * w * (w * v / sum2);
+                    }
+                }
+            }
+        }
+        let mut phi: Vec<Vec<Vec<f64>>> = delta
+            .iter()
+            .zip(&omega)
+            .map(|(row, weights)| {
+                row.iter()
+                    .zip(weights)
+                    .map(|(d, &w)| {
+                        if w > 0.0 {
+                            d.iter().map(|x| x / w).collect()
+                        } else {
+                            d.clone()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let supported: Vec<f64> = omega
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|&w| w > 0.0)
+            .collect();
+        let lambda = smoothing * supported.iter().sum::<f64>() / supported.len().max(1) as f64;
+        if lambda > 0.0 {
+            for _ in 0..SMOOTHING_SWEEPS {
+                for i in 0..m + 3 {
+                    for j in 0..n + 3 {
+                        let neighbours: Vec<(usize, usize)> = [
+                            (i.wrapping_sub(1), j),
+                            (i + 1, j),
+                            (i, j.wrapping_sub(1)),
+                            (i, j + 1),
+                        ]
+                        .into_iter()
+                        .filter(|&(k, l)| k < m + 3 && l < n + 3)
+                        .collect();
+                        let weight = omega[i][j] + lambda * neighbours.len() as f64;
+                        phi[i][j] = (0..dimension)
+                            .map(|d| {
+                                let pull: f64 = neighbours.iter().map(|&(k, l)| phi[k][l][d]).sum();
+                                (delta[i][j][d] + lambda * pull) / weight
+                            })
+                            .collect();
+                    }
+                }
+            }
+        }
+        Lattice { m, n, phi }
+    }
+
+    // The value at lattice coordinates (x, y)
+    fn eval(&self, x: f64, y: f64) -> Vec<f64> {
+        let (i, s) = cell(x, self.m);
+        let (j, t) = cell(y, self.n);
+        let (bs, bt) = (cubic_basis(s), cubic_basis(t));
+        let mut value = vec![0.0; self.phi[0][0].len()];
+        for (k, a) in bs.iter().enumerate() {
+            for (l, b) in bt.iter().enumerate() {
+                for (r, p) in value.iter_mut().zip(&self.phi[i + k][j + l]) {
+                    *r += a * b * p;
+                }
+            }
+        }
+        value
+    }
+
+    fn add(&mut self, other: &Lattice) {
+        for (row, other_row) in self.phi.iter_mut().zip(&other.phi) {
+            for (p, q) in row.iter_mut().zip(other_row) {
+                for (x, y) in p.iter_mut().zip(q) {
+                    *x += y;
+                }
+            }
+        }
+    }
+
+    // The same function on a lattice with half the spacing, applying the subdivision rules
+    // of uniform cubic B-Splines in both directions
+    fn refine(&self) -> Self {
+        let refined: Vec<Vec<Vec<f64>>> = self.phi.iter().map(|row| subdivide(row)).collect();
+        let columns = transpose(&refined);
+        let phi = transpose(&columns.iter().map(|c| subdivide(c)).collect::<Vec<_>>());
+        Lattice {
+            m: 2 * self.m,
+            n: 2 * self.n,
+            phi,
+        }
+    }
+}
+
+// Halves the spacing of a row of uniform cubic control points covering k cells,
+// the k + 3 points become 2k + 3 with p'(2i) = (p(i-1) + 6 p(i) + p(i+1)) / 8 and
+// p'(2i+1) = (p(i) + p(i+1)) / 2 for the lattice indices starting at -1
+fn subdivide(row: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let cells = row.len() - 3;
+    let mix = |terms: &[(f64, &Vec<f64>)]| -> Vec<f64> {
+        (0..row[0].len())
+            .map(|d| terms.iter().map(|(c, p)| c * p[d]).sum())
+            .collect()
+    };
+    (0..2 * cells + 3)
+        .map(|a| {
+            // the lattice index a - 1 of the refined row
+            if a % 2 == 1 {
+                let i = (a - 1) / 2;
+                mix(&[(0.125, &row[i]), (0.75, &row[i + 1]), (0.125, &row[i + 2])])
+            } else {
+                let i = a / 2;
+                mix(&[(0.5, &row[i]), (0.5, &row[i + 1])])
+            }
+        })
+        .collect()
+}
+
+// The cell index and local coordinate of lattice coordinate x in [0, cells]
+fn cell(x: f64, cells: usize) -> (usize, f64) {
+    let i = (x.floor().max(0.0) as usize).min(cells - 1);
+    (i, x - i as f64)
+}
+
+// The four uniform cubic B-Splines that are nonzero on a cell at local coordinate s
+fn cubic_basis(s: f64) -> [f64; 4] {
+    let r = 1.0 - s;
+    [
+        r * r * r / 6.0,
+        (3.0 * s * s * s - 6.0 * s * s + 4.0) / 6.0,
+        (-3.0 * s * s * s + 3.0 * s * s + 3.0 * s + 1.0) / 6.0,
+        s * s * s / 6.0,
+    ]
+}
+
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| {
+        (lo.min(x), hi.max(x))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::NURBSSurface;
+
+    // pseudo random points with heights sin(x) * cos(y) on [0, 4] x [0, 2]
+    fn terrain(count: usize) -> Vec<Vec<f64>> {
+        let mut seed: u64 = 12345;
+        let mut random = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        (0..count)
+            .map(|_| {
+                let (x, y) = (4.0 * random(), 2.0 * random());
+                vec![x, y, x.sin() * y.cos()]
+            })
+            .collect()
+    }
+
+    fn max_residual(surface: &NURBSSurface, points: &[Vec<f64>]) -> f64 {
+        points
+            .iter()
+            .map(|p| {
+                let s = surface.eval(p[0], p[1]).unwrap();
+                assert!((s[0] - p[0]).abs() < 1e-12 && (s[1] - p[1]).abs() < 1e-12);
+                (s[2] - p[2]).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_fit_height_field() {
+        let points = terrain(2000);
+        let coarse = NURBSSurface::fit_height_field(&points, 2, 0.0).unwrap();
+        let fine = NURBSSurface::fit_height_field(&points, 6, 0.0).unwrap();
+        // the first level has 2 x 1 cells for the 2:1 domain
+        assert_eq!(coarse.control_points().len(), 4 + 3);
+        assert_eq!(coarse.control_points()[0].len(), 2 + 3);
+        assert_eq!(fine.domain_u(), coarse.domain_u());
+        assert!(max_residual(&fine, &points) < 1e-2);
+        assert!(max_residual(&fine, &points) < max_residual(&coarse, &points));
+        // away from the data the function is still approximated
+        let z = fine.eval(2.0, 1.0).unwrap()[2];
+        assert!((z - 2.0_f64.sin() * 1.0_f64.cos()).abs() < 1e-2);
+        // smoothing trades accuracy for a smoother surface
+        let smooth = NURBSSurface::fit_height_field(&points, 6, 1.0).unwrap();
+        assert!(max_residual(&smooth, &points) > max_residual(&fine, &points));
+    }
+
+    #[test]
+    fn test_fit_smoothing() {
+        // the heights of the terrain at two very different scales, with a little noise
+        let points = terrain(500);
+        let roughness = |surface: &NURBSSurface| -> f64 {
+            let net = surface.control_points();
+            let mut sum = 0.0;
+            for i in 1..net.len() - 1 {
+                for j in 1..net[0].len() - 1 {
+                    let laplace =
+                        net[i - 1][j][2] + net[i + 1][j][2] + net[i][j - 1][2] + net[i][j + 1][2]
+                            - 4.0 * net[i][j][2];
+                    sum += laplace * laplace;
+                }
+            }
+            sum
+        };
+        for scale in [1e-3, 1e3] {
+            let noisy: Vec<Vec<f64>> = points
+                .iter()
+                .enumerate()
+                .map(|(k, p)| {
+                    let noise = if k % 2 == 0 { 0.05 } else { -0.05 };
+                    vec![p[0], p[1], scale * (p[2] + noise)]
+                })
+                .collect();
+            let plain = NURBSSurface::fit_height_field(&noisy, 5, 0.0).unwrap();
+            let smooth = NURBSSurface::fit_height_field(&noisy, 5, 0.5).unwrap();
+            assert!(roughness(&smooth) < 0.5 * roughness(&plain));
+        }
+        // smoothing does not pull the surface towards zero like damping would
+        let flat: Vec<Vec<f64>> = points.iter().map(|p| vec![p[0], p[1], 5.0]).collect();
+        let smooth = NURBSSurface::fit_height_field(&flat, 5, 10.0).unwrap();
+        assert!(max_residual(&smooth, &flat) < 1e-2 * 5.0);
+    }
+
+    #[test]
+    fn test_fit_scattered() {
+        // two values at every parameter of a regular pattern
+        let params: Vec<[f64; 2]> = (0..50)
+            .map(|k| [(k % 7) as f64 / 6.0, (k / 7) as f64 / 7.0])
+            .collect();
+        let values: Vec<Vec<f64>> = params
+            .iter()
+            .map(|p| vec![1.0 + p[0] + 2.0 * p[1], p[0] * p[1]])
+            .collect();
+        let surface = NURBSSurface::fit_scattered(&params, &values, 5, 0.0).unwrap();
+        for (p, value) in params.iter().zip(&values) {
+            let s = surface.eval(p[0], p[1]).unwrap();
+            assert!((s[0] - value[0]).abs() < 1e-2 && (s[1] - value[1]).abs() < 1e-2);
+        }
+        assert!(NURBSSurface::fit_scattered(&params, &values[1..], 3, 0.0).is_err());
+        assert!(
+            NURBSSurface::fit_scattered(&[[0.0, 0.0], [1.0, 0.0]], &values[..2], 3, 0.0).is_err()
+        );
+        assert!(NURBSSurface::fit_height_field(&[vec![0.0, 1.0]], 3, 0.0).is_err());
+        // the finest lattice is limited in size, also for long and thin domains
+        assert!(NURBSSurface::fit_scattered(&params, &values, 11, 0.0).is_err());
+        let thin = [[0.0, 0.0], [1e7, 1.0], [0.0, 1.0]];
+        assert!(NURBSSurface::fit_scattered(&thin, &values[..3], 1, 0.0).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+// nurbs/split.rs
+// Splitting curves and surfaces into pieces that exactly match the original over their range
+use crate::nurbs::knots::{clamp_rows, knot_multiplicity, refine_rows};
+use crate::nurbs::{NURBSCurve, NURBSSurface, Rows};
+
+// knot vector and rows of one of the pieces
+type Piece = (Vec<f64>, Rows);
+
+// Splits every row of homogeneous control points sharing `knots` at the interior
+// parameter `t`. The knot is first inserted up to multiplicity degree, then the two
+// halves share the control point at `t` and both get clamped knot vectors.
+pub(crate) fn split_rows(
+    degree: usize,
+    knots: &[f64],
+    rows: &[Vec<Vec<f64>>],
+    t: f64,
+) -> Result<(Piece, Piece), &'static str> {
+    let (start, end) = (knots[degree], knots[knots.len() - degree - 1]);
+    if t <= start || t >= end {
+        return Err("The parameter t must lie inside the domain to split");
+    }
+    let s = knot_multiplicity(knots, t);
+    if s > degree {
+        return Err("Knot multiplicity must not exceed the degree");
+    }
+    let (knots, rows) = clamp_rows(degree, knots, rows);
+    let (knots, rows) = refine_rows(degree, &knots, &rows, &vec![t; degree - s]);
+    // the first of the degree copies of t
+    let a = knots.iter().position(|&k| k == t).unwrap_or(0);
+
+    let mut left_knots = knots[..a + degree].to_vec();
+    left_knots.push(t);
+    let mut right_knots = vec![t];
+    right_knots.extend_from_slice(&knots[a..]);
+    let left = rows.iter().map(|row| row[..a].to_vec()).collect();
+    let right = rows.iter().map(|row| row[a - 1..].to_vec()).collect();
+    Ok(((left_knots, left), (right_knots, right)))
+}
+
+impl NURBSCurve {
+    // Splits the curve at the interior parameter `t` into the pieces before and after it.
+    // Both pieces keep the parameterization of the original curve over their range.
+    pub fn split(&self, t: f64) -> Result<(NURBSCurve, NURBSCurve), &'static str> {
+        let ((left_knots, left), (right_knots, right)) =
+            split_rows(self.degree(), self.knots(), &[self.homogeneous_points()], t)?;
+        Ok((
+            NURBSCurve::from_homogeneous(self.degree(), left_knots, &left[0])?,
+            NURBSCurve::from_homogeneous(self.degree(), right_knots, &right[0])?,
+        ))
+    }
+
+    // The piece of the curve between the parameters `t0` < `t1`, with clamped knots
+    pub fn subcurve(&self, t0: f64, t1: f64) -> Result<NURBSCurve, &'static str> {
+        let (start, end) = self.domain();
+        if t0 < start || t1 > end {
+            return Err("The parameter t is out of range of the knot values");
+        }
+        if t0 >= t1 {
+            return Err("t0 must be less than t1");
+        }
+        let (knots, rows) = clamp_rows(self.degree(), self.knots(), &[self.homogeneous_points()]);
+        let mut curve = NURBSCurve::from_homogeneous(self.degree(), knots, &rows[0])?;
+        if t0 > start {
+            curve = curve.split(t0)?.1;
+        }
+        if t1 < end {
+            curve = curve.split(t1)?.0;
+        }
+        Ok(curve)
+    }
+}
+
+impl NURBSSurface {
+    // Splits the surface at the interior parameter `u` into the pieces before and after it
+    pub fn split_u(&self, u: f64) -> Result<(NURBSSurface, NURBSSurface), &'static str> {
+        let ((left_knots, left), (right_knots, right)) =
+            split_rows(self.degree_u(), self.knots_u(), &self.rows_u(), u)?;
+        let piece = |knots_u, rows: &Rows| {
+            NURBSSurface::from_rows_u(
+                self.degree_u(),
+                self.degree_v(),
+                knots_u,
+                self.knots_v().to_vec(),
+                rows,
+            )
+        };
+        Ok((piece(left_knots, &left)?, piece(right_knots, &right)?))
+    }
+
+    // Splits the surface at the interior parameter `v` into the pieces before and after it
+    pub fn split_v(&self, v: f64) -> Result<(NURBSSurface, NURBSSurface), &'static str> {
+        let ((left_knots, left), (right_knots, right)) =
+            split_rows(self.degree_v(), self.knots_v(), &self.homogeneous_net(), v)?;
+        let piece = |knots_v, net: &Rows| {
+            NURBSSurface::from_homogeneous_net(
+                self.degree_u(),
+                self.degree_v(),
+                self.knots_u().to_vec(),
+                knots_v,
+                net,
+            )
+        };
+        Ok((piece(left_knots, &left)?, piece(right_knots, &right)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{norm, sub};
+
+    fn curve() -> NURBSCurve {
+        NURBSCurve::new(
+            3,
+            vec![0.0, 0.0, 0.0, 0.0, 0.4, 0.7, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0, 0.5, 2.0, 1.0, 1.0, 1.5],
+            vec![
+                vec![0.0, 0.0],
+                vec![1.0, 2.0],
+                vec![2.0, 2.0],
+                vec![3.0, 0.0],
+                vec![4.0, 1.0],
+                vec![5.0, 1.0],
+            ],
+        )
+        .unwrap()
+    }
+
+    fn assert_matches(piece: &NURBSCurve, curve: &NURBSCurve) {
+        let (start, end) = piece.domain();
+        assert_eq!(piece.knots()[0], start);
+        assert_eq!(piece.knots()[piece.knots().len() - 1], end);
+        for i in 0..=20 {
+            let t = start + (end - start) * i as f64 / 20.0;
+            assert!(norm(&sub(&piece.eval(t).unwrap(), &curve.eval(t).unwrap())) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_split() {
+        let curve = curve();
+        for &t in &[0.2, 0.4, 0.9] {
+            let (left, right) = curve.split(t).unwrap();
+            assert_eq!(left.domain(), (0.0, t));
+            assert_eq!(right.domain(), (t, 1.0));
+            assert_matches(&left, &curve);
+            assert_matches(&right, &curve);
+        }
+        assert!(curve.split(0.0).is_err());
+        assert!(curve.split(1.0).is_err());
+    }
+
+    #[test]
+    fn test_subcurve() {
+        let curve = curve();
+        let piece = curve.subcurve(0.25, 0.8).unwrap();
+        assert_eq!(piece.domain(), (0.25, 0.8));
+        assert_matches(&piece, &curve);
+        assert_eq!(curve.subcurve(0.0, 1.0).unwrap(), curve);
+        assert!(curve.subcurve(0.5, 0.5).is_err());
+    }
+
+    #[test]
+    fn test_split_surface() {
+        let knots = vec![0.0, 0.0, 0.0, 0.5, 1.0, 1.0, 1.0];
+        let weights = vec![vec![1.0, 2.0, 1.0, 1.0]; 4];
+        let control_points = (0..4)
+            .map(|i| {
+                (0..4)
+                    .map(|j| vec![i as f64, j as f64, ((i + 2 * j) % 3) as f64])
+                    .collect()
+            })
+            .collect();
+        let surface =
+            NURBSSurface::new(2, 2, knots.clone(), knots, weights, control_points).unwrap();
+        let (left, right) = surface.split_u(0.3).unwrap();
+        let (bottom, top) = surface.split_v(0.5).unwrap();
+        assert_eq!(left.domain_u(), (0.0, 0.3));
+        assert_eq!(top.domain_v(), (0.5, 1.0));
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (u, v) = (i as f64 / 10.0, j as f64 / 10.0);
+                let point = surface.eval(u, v).unwrap();
+                let piece = if u <= 0.3 { &left } else { &right };
+                assert!(norm(&sub(&point, &piece.eval(u, v).unwrap())) < 1e-12);
+                let piece = if v <= 0.5 { &bottom } else { &top };
+                assert!(norm(&sub(&point, &piece.eval(u, v).unwrap())) < 1e-12);
+            }
+        }
+        assert!(surface.split_u(1.0).is_err());
+    }
+}
@@ -0,0 +1,392 @@
+// nurbs/surface_geometry.rs
+// Differential geometry of NURBS surfaces built on NURBSSurface::derivatives()
+use crate::nurbs::NURBSSurface;
+use crate::vector::{add, cross, dot, norm, normalize, scale};
+
+// relative size below which Su x Sv counts as vanishing
+const NORMAL_TOLERANCE: f64 = 1e-10;
+
+// The coefficients of the first (E, F, G) and second (L, M, N) fundamental forms at a
+// surface point, I = E du^2 + 2 F du dv + G dv^2 and II = L du^2 + 2 M du dv + N dv^2.
+// The second form is measured along normal(), so its sign flips with the orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FundamentalForms {
+    pub e: f64,
+    pub f: f64,
+    pub g: f64,
+    pub l: f64,
+    pub m: f64,
+    pub n: f64,
+}
+
+impl FundamentalForms {
+    // K = (LN - M^2) / (EG - F^2)
+    pub fn gaussian_curvature(&self) -> f64 {
+        (self.l * self.n - self.m * self.m) / (self.e * self.g - self.f * self.f)
+    }
+
+    // H = (EN + GL - 2FM) / (2 (EG - F^2))
+    pub fn mean_curvature(&self) -> f64 {
+        (self.e * self.n + self.g * self.l - 2.0 * self.f * self.m)
+            / (2.0 * (self.e * self.g - self.f * self.f))
+    }
+}
+
+// The principal curvatures k1 >= k2 at a surface point and the unit tangent
+// directions they are attained in
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipalCurvatures {
+    pub k1: f64,
+    pub k2: f64,
+    pub direction1: Vec<f64>,
+    pub direction2: Vec<f64>,
+}
+
+impl PrincipalCurvatures {
+    pub fn gaussian(&self) -> f64 {
+        self.k1 * self.k2
+    }
+
+    pub fn mean(&self) -> f64 {
+        0.5 * (self.k1 + self.k2)
+    }
+}
+
+// One sample of a curvature map, `curvatures` is None where they are undefined
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurvatureSample {
+    pub u: f64,
+    pub v: f64,
+    pub point: Vec<f64>,
+    pub curvatures: Option<PrincipalCurvatures>,
+}
+
+impl NURBSSurface {
+    // The unit normal Su x Sv / |Su x Sv| of a 3D surface at (u, v).
+    // Where the cross product vanishes, e.g. at the poles of revolved surfaces or on
+    // collapsed edges, the normal is the limit approaching (u, v) from inside the domain.
+    // Moving by h along u the cross product grows like h * (Suu x Sv + Su x Suv), and if
+    // both tangents vanish like h^2 * (Suu x Suv), the same holds for v. The direction
+    // along the tangent that did not collapse is tried first.
+    pub fn normal(&self, u: f64, v: f64) -> Result<Vec<f64>, &'static str> {
+        let ders = self.derivatives(u, v, 2)?;
+        if ders[0][0].len() != 3 {
+            return Err("Normals are only defined for 3D surfaces");
+        }
+        let (su, sv) = (&ders[1][0], &ders[0][1]);
+        let (suu, suv, svv) = (&ders[2][0], &ders[1][1], &ders[0][2]);
+        let first = norm(su) + norm(sv);
+        let second = norm(suu) + norm(suv) + norm(svv);
+
+        let n = cross(su, sv);
+        if norm(&n) > NORMAL_TOLERANCE * first * first {
+            return normalize(&n).ok_or("The normal is undefined at (u, v)");
+        }
+
+        // the limits are taken from the side of (u, v) that lies inside the domain
+        let du = if u < self.domain_u().1 { 1.0 } else { -1.0 };
+        let dv = if v < self.domain_v().1 { 1.0 } else { -1.0 };
+        let along_u = scale(&add(&cross(suu, sv), &cross(su, suv)), du);
+        let along_v = scale(&add(&cross(suv, sv), &cross(su, svv)), dv);
+        let mut limits = if norm(sv) <= norm(su) {
+            vec![(along_u, first), (along_v, first)]
+        } else {
+            vec![(along_v, first), (along_u, first)]
+        };
+        limits.push((cross(suu, suv), second));
+        limits.push((cross(suv, svv), second));
+        limits
+            .iter()
+            .find(|(limit, size)| norm(limit) > NORMAL_TOLERANCE * size * second)
+            .and_then(|(limit, _)| normalize(limit))
+            .ok_or("The normal is undefined at (u, v)")
+    }
+
+    // The first and second fundamental forms of a 3D surface at (u, v).
+    // Fails where the tangents are parallel, since the curvatures are undefined there.
+    pub fn fundamental_forms(&self, u: f64, v: f64) -> Result<FundamentalForms, &'static str> {
+        let (forms, _) = self.forms_and_tangents(u, v)?;
+        Ok(forms)
+    }
+
+    pub fn gaussian_curvature(&self, u: f64, v: f64) -> Result<f64, &'static str> {
+        Ok(self.fundamental_forms(u, v)?.gaussian_curvature())
+    }
+
+    pub fn mean_curvature(&self, u: f64, v: f64) -> Result<f64, &'static str> {
+        Ok(self.fundamental_forms(u, v)?.mean_curvature())
+    }
+
+    // The principal curvatures k = H +- sqrt(H^2 - K) and their directions, the
+    // eigenvectors (du, dv) of (II - k I) mapped to 3D as du * Su + dv * Sv.
+    // At umbilics every direction is principal and direction1 is any tangent direction.
+    // direction2 = normal x direction1, so both are orthonormal.
+    pub fn principal_curvatures(
+        &self,
+        u: f64,
+        v: f64,
+    ) -> Result<PrincipalCurvatures, &'static str> {
+        let (forms, [su, sv, normal]) = self.forms_and_tangents(u, v)?;
+        let FundamentalForms { e, f, g, l, m, n } = forms;
+        let mean = forms.mean_curvature();
+        let root = (mean * mean - forms.gaussian_curvature()).max(0.0).sqrt();
+        let (k1, k2) = (mean + root, mean - root);
+
+        // the larger row of the singular 2x2 system gives the most accurate direction
+        let rows = [(l - k1 * e, m - k1 * f), (m - k1 * f, n - k1 * g)];
+        let (a, b) = if rows[0].0.hypot(rows[0].1) >= rows[1].0.hypot(rows[1].1) {
+            rows[0]
+        } else {
+            rows[1]
+        };
+        // at umbilics both rows vanish and any direction will do
+        let direction1 = normalize(&add(&scale(&su, -b), &scale(&sv, a)))
+            .or_else(|| normalize(&su))
+            .ok_or("The tangent vanishes at (u, v)")?;
+        let direction2 = cross(&normal, &direction1);
+        Ok(PrincipalCurvatures {
+            k1,
+            k2,
+            direction1,
+            direction2,
+        })
+    }
+
+    // Samples the curvatures on a uniform grid of samples_u x samples_v parameters over the
+    // domain, e.g. for a heat map of the Gaussian or mean curvature. The result is indexed
+    // [i][j] for the i-th u and j-th v parameter.
+    pub fn curvature_map(
+        &self,
+        samples_u: usize,
+        samples_v: usize,
+    ) -> Result<Vec<Vec<CurvatureSample>>, &'static str> {
+        if samples_u < 2 || samples_v < 2 {
+            return Err("At least two samples are needed");
+        }
+        if self.control_points()[0][0].len() != 3 {
+            return Err("Curvatures are only defined for 3D surfaces");
+        }
+        let (u_start, u_end) = self.domain_u();
+        let (v_start, v_end) = self.domain_v();
+        (0..samples_u)
+            .map(|i| {
+                let u = u_start + (u_end - u_start) * i as f64 / (samples_u - 1) as f64;
+                (0..samples_v)
+                    .map(|j| {
+                        let v = v_start + (v_end - v_start) * j as f64 / (samples_v - 1) as f64;
+                        Ok(CurvatureSample {
+                            u,
+                            v,
+                            point: self.eval(u, v)?,
+                            curvatures: self.principal_curvatures(u, v).ok(),
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // The fundamental forms together with Su, Sv and the unit normal
+    fn forms_and_tangents(
+        &self,
+        u: f64,
+        v: f64,
+    ) -> Result<(FundamentalForms, [Vec<f64>; 3]), &'static str> {
+        let ders = self.derivatives(u, v, 2)?;
+        if ders[0][0].len() != 3 {
+            return Err("Curvatures are only defined for 3D surfaces");
+        }
+        let (su, sv) = (&ders[1][0], &ders[0][1]);
+        let (e, f, g) = (dot(su, su), dot(su, sv), dot(sv, sv));
+        if e * g - f * f <= NORMAL_TOLERANCE * NORMAL_TOLERANCE * e * g || e * g == 0.0 {
+            return Err("The curvature is undefined where the tangents are parallel");
+        }
+        let normal = normalize(&cross(su, sv)).ok_or("The normal is undefined at (u, v)")?;
+        let forms = FundamentalForms {
+            e,
+            f,
+            g,
+            l: dot(&ders[2][0], &normal),
+            m: dot(&ders[1][1], &normal),
+            n: dot(&ders[0][2], &normal),
+        };
+        Ok((forms, [su.clone(), sv.clone(), normal]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::NURBSSurface;
+    use crate::vector::{dot, norm, sub};
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    // unit sphere, a half circle from the south to the north pole (u) revolved
+    // around the z axis (v), both poles are degenerate
+    fn sphere() -> NURBSSurface {
+        let w = FRAC_1_SQRT_2;
+        let profile = [
+            ([0.0, -1.0], 1.0),
+            ([1.0, -1.0], w),
+            ([1.0, 0.0], 1.0),
+            ([1.0, 1.0], w),
+            ([0.0, 1.0], 1.0),
+        ];
+        let circle = [
+            ([1.0, 0.0], 1.0),
+            ([1.0, 1.0], w),
+            ([0.0, 1.0], 1.0),
+            ([-1.0, 1.0], w),
+            ([-1.0, 0.0], 1.0),
+            ([-1.0, -1.0], w),
+            ([0.0, -1.0], 1.0),
+            ([1.0, -1.0], w),
+            ([1.0, 0.0], 1.0),
+        ];
+        let control_points = profile
+            .iter()
+            .map(|(p, _)| {
+                circle
+                    .iter()
+                    .map(|(c, _)| vec![p[0] * c[0], p[0] * c[1], p[1]])
+                    .collect()
+            })
+            .collect();
+        let weights = profile
+            .iter()
+            .map(|(_, wp)| circle.iter().map(|(_, wc)| wp * wc).collect())
+            .collect();
+        NURBSSurface::new(
+            2,
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 2.0],
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0, 4.0],
+            weights,
+            control_points,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sphere_normal() {
+        // with u running north and v counterclockwise the normal points inwards
+        let sphere = sphere();
+        for &u in &[0.0, 0.3, 1.0, 1.7, 2.0] {
+            for &v in &[0.0, 0.5, 1.5, 2.9, 4.0] {
+                let point = sphere.eval(u, v).unwrap();
+                let normal = sphere.normal(u, v).unwrap();
+                assert!(norm(&sub(&normal, &[-point[0], -point[1], -point[2]])) < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sphere_curvature() {
+        // a unit sphere with inwards normals bends towards them with k = 1 everywhere
+        let sphere = sphere();
+        for &(u, v) in &[(0.3, 0.5), (1.0, 1.5), (1.7, 2.9)] {
+            assert!((sphere.gaussian_curvature(u, v).unwrap() - 1.0).abs() < 1e-9);
+            assert!((sphere.mean_curvature(u, v).unwrap() - 1.0).abs() < 1e-9);
+            let principal = sphere.principal_curvatures(u, v).unwrap();
+            assert!((principal.k1 - 1.0).abs() < 1e-6 && (principal.k2 - 1.0).abs() < 1e-6);
+            assert!(dot(&principal.direction1, &principal.direction2).abs() < 1e-12);
+        }
+        // the curvatures are undefined at the poles
+        assert!(sphere.principal_curvatures(0.0, 1.0).is_err());
+        let map = sphere.curvature_map(3, 5).unwrap();
+        assert_eq!((map.len(), map[0].len()), (3, 5));
+        assert!(map[0].iter().all(|sample| sample.curvatures.is_none()));
+        let sample = &map[1][2];
+        assert_eq!((sample.u, sample.v), (1.0, 2.0));
+        let curvatures = sample.curvatures.as_ref().unwrap();
+        assert!((curvatures.gaussian() - 1.0).abs() < 1e-9);
+        assert!((curvatures.mean() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_saddle_curvature() {
+        // the saddle z = u * v has K = -1, H = 0 at the origin, where it bends up along
+        // the diagonal and down along the other one
+        let saddle = NURBSSurface::new(
+            1,
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+            vec![
+                vec![vec![0.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]],
+                vec![vec![1.0, 0.0, 0.0], vec![1.0, 1.0, 1.0]],
+            ],
+        )
+        .unwrap();
+        let forms = saddle.fundamental_forms(0.0, 0.0).unwrap();
+        assert_eq!((forms.e, forms.f, forms.g), (1.0, 0.0, 1.0));
+        assert_eq!((forms.l, forms.m, forms.n), (0.0, 1.0, 0.0));
+        assert_eq!(forms.gaussian_curvature(), -1.0);
+        assert_eq!(forms.mean_curvature(), 0.0);
+        let principal = saddle.principal_curvatures(0.0, 0.0).unwrap();
+        assert_eq!((principal.k1, principal.k2), (1.0, -1.0));
+        let d1 = &principal.direction1;
+        assert!((d1[0] - d1[1]).abs() < 1e-12 && d1[2].abs() < 1e-12);
+        let d2 = &principal.direction2;
+        assert!((d2[0] + d2[1]).abs() < 1e-12 && d2[2].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_saddle_fundamental_forms() {
+        // the saddle z = u * v is flat at the origin but twisted, M = 1
+        let saddle = NURBSSurface::new(
+            1,
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+            vec![
+                vec![vec![0.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]],
+                vec![vec![1.0, 0.0, 0.0], vec![1.0, 1.0, 1.0]],
+            ],
+        )
+        .unwrap();
+        let forms = saddle.fundamental_forms(0.0, 0.0).unwrap();
+        assert_eq!((forms.e, forms.f, forms.g), (1.0, 0.0, 1.0));
+        assert_eq!((forms.l, forms.m, forms.n), (0.0, 1.0, 0.0));
+        // at (1, 1) Su = (1, 0, 1) and Sv = (0, 1, 1)
+        let forms = saddle.fundamental_forms(1.0, 1.0).unwrap();
+        assert_eq!((forms.e, forms.f, forms.g), (2.0, 1.0, 2.0));
+        // the tangents are parallel on the collapsed edge of a sphere
+        assert!(sphere().fundamental_forms(0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_collapsed_edge_normal() {
+        // S = (v, u * v, 0), the edge v = 0 collapses to the origin
+        let triangle = NURBSSurface::new(
+            1,
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+            vec![
+                vec![vec![0.0, 0.0, 0.0], vec![1.0, 0.0, 0.0]],
+                vec![vec![0.0, 0.0, 0.0], vec![1.0, 1.0, 0.0]],
+            ],
+        )
+        .unwrap();
+        for &(u, v) in &[(0.5, 0.5), (0.0, 0.0), (1.0, 0.0), (0.3, 0.0)] {
+            assert_eq!(triangle.normal(u, v).unwrap(), vec![0.0, 0.0, -1.0]);
+        }
+        // surfaces in the plane have no normal
+        let planar = NURBSSurface::new(
+            1,
+            1,
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![1.0, 1.0]],
+            vec![
+                vec![vec![0.0, 0.0], vec![1.0, 0.0]],
+                vec![vec![0.0, 1.0], vec![1.0, 1.0]],
+            ],
+        )
+        .unwrap();
+        assert!(planar.normal(0.5, 0.5).is_err());
+    }
+}
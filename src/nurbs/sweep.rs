@@ -0,0 +1,279 @@
+// nurbs/sweep.rs
+// Sweep surfaces that move a profile curve along one or two rail curves.
+// The exact sweep is generally not a NURBS surface, so copies of the profile are placed
+// along the rails and skinned, doubling their number until the surface between them
+// stays within the tolerance.
+use crate::nurbs::construction::skin;
+use crate::nurbs::knots::span_samples;
+use crate::nurbs::{NURBSCurve, NURBSSurface};
+use crate::vector::{add, cross, dot, norm, normalize, scale, sub};
+
+// sections placed initially per span of the rails, and the limit of all sections
+const SECTIONS_PER_SPAN: usize = 2;
+const MAX_SECTIONS: usize = 1024;
+// the sections are skinned with this degree, which needs degree + 1 of them
+const DEGREE_V: usize = 3;
+
+// Where a copy of the profile goes: the local coordinates of a point are the factors
+// of the axes, which are perpendicular but scaled for the two rail sweep
+struct Placement {
+    s: f64,
+    origin: Vec<f64>,
+    axes: [Vec<f64>; 3],
+}
+
+impl Placement {
+    fn local(&self, point: &[f64]) -> Vec<f64> {
+        let offset = sub(point, &self.origin);
+        self.axes
+            .iter()
+            .map(|axis| dot(&offset, axis) / dot(axis, axis))
+            .collect()
+    }
+
+    fn place(&self, local: &[f64]) -> Vec<f64> {
+        self.axes
+            .iter()
+            .zip(local)
+            .fold(self.origin.clone(), |p, (axis, &c)| {
+                add(&p, &scale(axis, c))
+            })
+    }
+}
+
+// The parameter of `curve` at the fraction `s` of its domain
+fn rail_param(curve: &NURBSCurve, s: f64) -> f64 {
+    let (start, end) = curve.domain();
+    start + (end - start) * s
+}
+
+fn check_curves(curves: &[&NURBSCurve], tolerance: f64) -> Result<(), &'static str> {
+    if curves.iter().any(|c| c.control_points()[0].len() != 3) {
+        return Err("The profile and the rails must be three dimensional");
+    }
+    if tolerance <= 0.0 {
+        return Err("The tolerance must be positive");
+    }
+    Ok(())
+}
+
+// Skins copies of the profile placed at uniformly spaced fractions s of the rails, and
+// checks the surface against the exact sweep halfway between them at the profile's
+// sample points. `place` returns the placements for increasing fractions in [0, 1],
+// the profile is given relative to the first one.
+fn sweep_sections(
+    profile: &NURBSCurve,
+    spans: usize,
+    tolerance: f64,
+    place: impl Fn(&[f64]) -> Result<Vec<Placement>, &'static str>,
+) -> Result<NURBSSurface, &'static str> {
+    let samples_u = span_samples(&profile.breakpoints(), 2 * (profile.degree() + 1));
+    let mut spans = spans.max(SECTIONS_PER_SPAN).max(DEGREE_V);
+    loop {
+        let params: Vec<f64> = (0..=2 * spans)
+            .map(|i| i as f64 / (2 * spans) as f64)
+            .collect();
+        let placements = place(&params)?;
+        let local_points: Vec<Vec<f64>> = profile
+            .control_points()
+            .iter()
+            .map(|p| placements[0].local(p))
+            .collect();
+        let local_samples = samples_u
+            .iter()
+            .map(|&u| Ok(placements[0].local(&profile.eval(u)?)))
+            .collect::<Result<Vec<_>, &'static str>>()?;
+
+        let sections = placements
+            .iter()
+            .step_by(2)
+            .map(|placement| {
+                NURBSCurve::new(
+                    profile.degree(),
+                    profile.knots().to_vec(),
+                    profile.weights().to_vec(),
+                    local_points.iter().map(|p| placement.place(p)).collect(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let params_v: Vec<f64> = params.iter().step_by(2).copied().collect();
+        let surface = skin(&sections, &params_v, DEGREE_V)?;
+
+        let mut error: f64 = 0.0;
+        for placement in placements.iter().skip(1).step_by(2) {
+            for (&u, local) in samples_u.iter().zip(&local_samples) {
+                let point = surface.eval(u, placement.s)?;
+                error = error.max(norm(&sub(&point, &placement.place(local))));
+            }
+        }
+        if error <= tolerance {
+            return Ok(surface);
+        }
+        if 2 * spans > MAX_SECTIONS {
+            return Err("The sweep cannot be approximated within the tolerance");
+        }
+        spans *= 2;
+    }
+}
+
+impl NURBSSurface {
+    // Sweeps the profile along the rail, S(u, v) is the profile point at u carried to the
+    // fraction v of the rail's domain. The profile is given in place at the start of the
+    // rail and moves rigidly with the rotation minimizing frame of the rail, so it does
+    // not twist. The rail must have a nonvanishing tangent. The result is cubic in v.
+    // The deviation from the exact sweep is only checked at samples of the profile halfway
+    // between neighbouring sections, which must be within `tolerance`; elsewhere it is
+    // expected but not guaranteed to be as small.
+    pub fn sweep(
+        profile: &NURBSCurve,
+        rail: &NURBSCurve,
+        tolerance: f64,
+    ) -> Result<NURBSSurface, &'static str> {
+        check_curves(&[profile, rail], tolerance)?;
+        let spans = (rail.breakpoints().len() - 1) * SECTIONS_PER_SPAN;
+        sweep_sections(profile, spans, tolerance, |params| {
+            let rail_params: Vec<f64> = params.iter().map(|&s| rail_param(rail, s)).collect();
+            let frames = rail.rotation_minimizing_frames_at(&rail_params)?;
+            Ok(frames
+                .into_iter()
+                .zip(params)
+                .map(|(frame, &s)| Placement {
+                    s,
+                    origin: frame.point,
+                    axes: [frame.normal, frame.binormal, frame.tangent],
+                })
+                .collect())
+        })
+    }
+
+    // Sweeps the profile along two rails, which are matched by the fractions of their
+    // domains. The profile is given in place between the starts of the rails, and every
+    // copy is rotated and scaled uniformly so that the start points of the rails follow
+    // the rails, which keeps a profile running from rail to rail on both of them. The
+    // copies face the average tangent direction of the rails. Approximates within
+    // `tolerance` like sweep().
+    pub fn sweep_two_rails(
+        profile: &NURBSCurve,
+        rail1: &NURBSCurve,
+        rail2: &NURBSCurve,
+        tolerance: f64,
+    ) -> Result<NURBSSurface, &'static str> {
+        check_curves(&[profile, rail1, rail2], tolerance)?;
+        let spans =
+            (rail1.breakpoints().len() - 1).max(rail2.breakpoints().len() - 1) * SECTIONS_PER_SPAN;
+        let start_width = norm(&sub(
+            &rail2.eval(rail2.domain().0)?,
+            &rail1.eval(rail1.domain().0)?,
+        ));
+        if start_width == 0.0 {
+            return Err("The rails must not start at the same point");
+        }
+        sweep_sections(profile, spans, tolerance, |params| {
+            params
+                .iter()
+                .map(|&s| {
+                    let first = rail1.derivatives(rail_param(rail1, s), 1)?;
+                    let second = rail2.derivatives(rail_param(rail2, s), 1)?;
+                    let across = sub(&second[0], &first[0]);
+                    let x_axis = normalize(&across).ok_or("The rails must not meet")?;
+                    let tangent = add(
+                        &normalize(&first[1]).ok_or("The tangent of a rail vanishes")?,
+                        &normalize(&second[1]).ok_or("The tangent of a rail vanishes")?,
+                    );
+                    let z_axis = normalize(&sub(&tangent, &scale(&x_axis, dot(&tangent, &x_axis))))
+                        .ok_or("The rails must not run along the line between them")?;
+                    let y_axis = cross(&z_axis, &x_axis);
+                    let factor = norm(&across) / start_width;
+                    Ok(Placement {
+                        s,
+                        origin: first[0].clone(),
+                        axes: [
+                            scale(&x_axis, factor),
+                            scale(&y_axis, factor),
+                            scale(&z_axis, factor),
+                        ],
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{norm, sub};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_sweep() {
+        // a small circle around the start of a quarter circle rail sweeps a quarter torus
+        let rail = NURBSCurve::arc(
+            &[0.0, 0.0, 0.0],
+            &[1.0, 0.0, 0.0],
+            &[0.0, 1.0, 0.0],
+            3.0,
+            0.0,
+            PI / 2.0,
+        )
+        .unwrap();
+        let profile =
+            NURBSCurve::circle(&[3.0, 0.0, 0.0], &[1.0, 0.0, 0.0], &[0.0, 0.0, 1.0], 0.5).unwrap();
+        let tolerance = 1e-4;
+        let torus = NURBSSurface::sweep(&profile, &rail, tolerance).unwrap();
+        for i in 0..=20 {
+            for j in 0..=20 {
+                let p = torus.eval(i as f64 / 20.0, j as f64 / 20.0).unwrap();
+                let tube = ((p[0].hypot(p[1]) - 3.0).powi(2) + p[2] * p[2]).sqrt();
+                assert!((tube - 0.5).abs() < tolerance);
+            }
+        }
+        // the profile stays in place at the start and ends up in the yz plane
+        for i in 0..=10 {
+            let u = i as f64 / 10.0;
+            let start = torus.eval(u, 0.0).unwrap();
+            assert!(norm(&sub(&start, &profile.eval(u).unwrap())) < 1e-12);
+            assert!(torus.eval(u, 1.0).unwrap()[0].abs() < 1e-9);
+        }
+        assert!(NURBSSurface::sweep(&profile, &rail, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_sweep_two_rails() {
+        // a half circle between two straight rails that move apart
+        let line = |from: [f64; 3], to: [f64; 3]| {
+            NURBSCurve::new(
+                1,
+                vec![0.0, 0.0, 1.0, 1.0],
+                vec![1.0; 2],
+                vec![from.to_vec(), to.to_vec()],
+            )
+            .unwrap()
+        };
+        let rail1 = line([0.0, 0.0, 0.0], [0.0, 10.0, 0.0]);
+        let rail2 = line([1.0, 0.0, 0.0], [3.0, 10.0, 0.0]);
+        let profile = NURBSCurve::arc(
+            &[0.5, 0.0, 0.0],
+            &[-1.0, 0.0, 0.0],
+            &[0.0, 0.0, 1.0],
+            0.5,
+            0.0,
+            PI,
+        )
+        .unwrap();
+        let swept = NURBSSurface::sweep_two_rails(&profile, &rail1, &rail2, 1e-6).unwrap();
+        // cubic in v even though the rails have a single span
+        assert_eq!(swept.degree_v(), 3);
+        for i in 0..=10 {
+            let v = i as f64 / 10.0;
+            let first = swept.eval(0.0, v).unwrap();
+            let second = swept.eval(1.0, v).unwrap();
+            assert!(norm(&sub(&first, &rail1.eval(v).unwrap())) < 1e-6);
+            assert!(norm(&sub(&second, &rail2.eval(v).unwrap())) < 1e-6);
+            // the half circle scales with the distance 1 + 2v between the rails
+            let top = swept.eval(0.5, v).unwrap();
+            assert!((top[2] - 0.5 * (1.0 + 2.0 * v)).abs() < 1e-6);
+        }
+        assert!(NURBSSurface::sweep_two_rails(&profile, &rail1, &rail1, 1e-6).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+// nurbs/tessellate.rs
+// Approximating curves with polylines and surfaces with triangle meshes
+use crate::nurbs::knots::span_samples;
+use crate::nurbs::{Mesh, NURBSCurve, NURBSSurface};
+use crate::vector::{add, cartesian, cross, dot, homogeneous, norm, normalize, scale, sub};
+
+// intervals are not split below this fraction of the domain
+const MIN_INTERVAL: f64 = 1.0 / (1u64 << 20) as f64;
+// upper bound for the refinement passes over the parameter grid
+const MAX_PASSES: usize = 32;
+
+impl NURBSCurve {
+    // Approximates the curve with a polyline whose chordal deviation is at most
+    // `tolerance`, returned as (t, point) pairs. Every knot span is converted to a
+    // rational Bézier segment and halved recursively with de Casteljau's algorithm until
+    // all control points of each piece are within the tolerance of its chord. For positive
+    // weights the piece lies in the convex hull of its control points, so this bounds the
+    // deviation everywhere and not just at samples. There is a vertex at every knot,
+    // so kinks stay sharp. Fails for weights that are not positive, and if a piece is still
+    // not flat enough when it has shrunk to MIN_INTERVAL of the domain.
+    pub fn tessellate(&self, tolerance: f64) -> Result<Vec<(f64, Vec<f64>)>, &'static str> {
+        if tolerance <= 0.0 {
+            return Err("The tolerance must be positive");
+        }
+        if self.weights().iter().any(|&w| w <= 0.0) {
+            return Err("The weights must be positive");
+        }
+        let (start, end) = self.domain();
+        let min_interval = (end - start) * MIN_INTERVAL;
+        let segments = self.to_bezier();
+        let mut polyline = vec![(start, self.eval(start)?)];
+        for segment in &segments {
+            let points_w = homogeneous(&segment.control_points, &segment.weights);
+            subdivide(
+                &points_w,
+                (segment.t0, segment.t1),
+                tolerance,
+                min_interval,
+                &mut polyline,
+            )?;
+            polyline.push((segment.t1, self.eval(segment.t1)?));
+        }
+        Ok(polyline)
+    }
+}
+
+// Pushes the vertices strictly between the ends of the Bézier piece with homogeneous
+// control points `points_w` over `interval` that keep it within tolerance of the polyline
+fn subdivide(
+    points_w: &[Vec<f64>],
+    (t0, t1): (f64, f64),
+    tolerance: f64,
+    min_interval: f64,
+    polyline: &mut Vec<(f64, Vec<f64>)>,
+) -> Result<(), &'static str> {
+    let (points, _) = cartesian(points_w);
+    let (first, last) = (&points[0], &points[points.len() - 1]);
+    if points
+        .iter()
+        .all(|p| distance_to_segment(p, first, last) <= tolerance)
+    {
+        return Ok(());
+    }
+    if t1 - t0 <= min_interval {
+        return Err("The curve cannot be tessellated within the tolerance");
+    }
+    // de Casteljau at s = 1/2 gives the control points of both halves
+    let mut left = Vec::with_capacity(points_w.len());
+    let mut right = Vec::with_capacity(points_w.len());
+    let mut level = points_w.to_vec();
+    while !level.is_empty() {
+        left.push(level[0].clone());
+        right.push(level[level.len() - 1].clone());
+        level = level
+            .windows(2)
+            .map(|pair| scale(&add(&pair[0], &pair[1]), 0.5))
+            .collect();
+    }
+    right.reverse();
+    let mid = 0.5 * (t0 + t1);
+    subdivide(&left, (t0, mid), tolerance, min_interval, polyline)?;
+    polyline.push((mid, cartesian(&right[..1]).0.swap_remove(0)));
+    subdivide(&right, (mid, t1), tolerance, min_interval, polyline)
+}
+
+impl NURBSSurface {
+    // Triangulates the surface on a uniform grid of samples_u x samples_v parameters
+    pub fn tessellate_uniform(
+        &self,
+        samples_u: usize,
+        samples_v: usize,
+    ) -> Result<Mesh, &'static str> {
+        if samples_u < 2 || samples_v < 2 {
+            return Err("At least two samples are needed");
+        }
+        let uniform = |(start, end): (f64, f64), samples: usize| -> Vec<f64> {
+            (0..samples)
+                .map(|i| start + (end - start) * i as f64 / (samples - 1) as f64)
+                .collect()
+        };
+        self.grid_mesh(
+            &uniform(self.domain_u(), samples_u),
+            &uniform(self.domain_v(), samples_v),
+        )
+    }
+
+    // Triangulates the surface on a grid that is refined until the midpoints of all grid
+    // edges and cells are within `tolerance` of the straight edge, and the normals at the
+    // ends of every grid edge differ by at most `max_angle` (in radians).
+    // The grid starts with every knot span divided into `degree` intervals and keeps one
+    // vertex at every knot, intervals are halved along whole grid lines so the mesh has
+    // no cracks or T-junctions. Fails if an interval would have to be split below
+    // MIN_INTERVAL of the domain, or if the grid does not settle within MAX_PASSES.
+    pub fn tessellate(&self, tolerance: f64, max_angle: f64) -> Result<Mesh, &'static str> {
+        if tolerance <= 0.0 || max_angle <= 0.0 {
+            return Err("The tolerance and the maximum angle must be positive");
+        }
+        if self.control_points()[0][0].len() != 3 {
+            return Err("Meshes can only be built from 3D surfaces");
+        }
+        let mut us = span_samples(&self.breakpoints_u(), self.degree_u());
+        let mut vs = span_samples(&self.breakpoints_v(), self.degree_v());
+        let max_cos = max_angle.cos();
+        for _ in 0..MAX_PASSES {
+            let along_u = |a: f64, b: f64| (a, b);
+            let along_v = |a: f64, b: f64| (b, a);
+            let refined_u = self.refine_grid(&us, &vs, along_u, tolerance, max_cos)?;
+            let refined_v = self.refine_grid(&vs, &us, along_v, tolerance, max_cos)?;
+            if refined_u.len() == us.len() && refined_v.len() == vs.len() {
+                return self.grid_mesh(&us, &vs);
+            }
+            us = refined_u;
+            vs = refined_v;
+        }
+        Err("The surface cannot be tessellated within the tolerance")
+    }
+
+    // Halves every interval of `params` that is too coarse across any of the `others`
+    // grid lines or the midpoints between them. `uv` maps (param, other) to (u, v).
+    fn refine_grid(
+        &self,
+        params: &[f64],
+        others: &[f64],
+        uv: impl Fn(f64, f64) -> (f64, f64),
+        tolerance: f64,
+        max_cos: f64,
+    ) -> Result<Vec<f64>, &'static str> {
+        let min_interval = (params[params.len() - 1] - params[0]) * MIN_INTERVAL;
+        let mut lines = span_samples(others, 2);
+        lines.dedup();
+        let mut refined = vec![params[0]];
+        for interval in params.windows(2) {
+            let (a0, a1) = (interval[0], interval[1]);
+            let mid = 0.5 * (a0 + a1);
+            let mut split = false;
+            for &b in &lines {
+                let (u0, v0) = uv(a0, b);
+                let (u1, v1) = uv(a1, b);
+                let (um, vm) = uv(mid, b);
+                let chord = scale(&add(&self.eval(u0, v0)?, &self.eval(u1, v1)?), 0.5);
+                if norm(&sub(&self.eval(um, vm)?, &chord)) > tolerance {
+                    split = true;
+                    break;
+                }
+                // normals are skipped where they are undefined
+                if let (Ok(n0), Ok(n1)) = (self.normal(u0, v0), self.normal(u1, v1)) {
+                    if dot(&n0, &n1) < max_cos {
+                        split = true;
+                        break;
+                    }
+                }
+            }
+            if split {
+                if a1 - a0 <= min_interval {
+                    return Err("The surface cannot be tessellated within the tolerance");
+                }
+                refined.push(mid);
+            }
+            refined.push(a1);
+        }
+        Ok(refined)
+    }
+
+    // Triangulates the grid us x vs, each grid cell becomes two triangles and
+    // triangles without area, e.g. next to a pole, are left out. Where not even a limit
+    // normal exists, e.g. at a pointed pole of a surface of revolution, a vertex gets the
+    // average normal of the grid cells around it.
+    fn grid_mesh(&self, us: &[f64], vs: &[f64]) -> Result<Mesh, &'static str> {
+        if self.control_points()[0][0].len() != 3 {
+            return Err("Meshes can only be built from 3D surfaces");
+        }
+        let mut mesh = Mesh::default();
+        let mut normals = Vec::with_capacity(us.len() * vs.len());
+        for &u in us {
+            for &v in vs {
+                mesh.positions.push(self.eval(u, v)?);
+                normals.push(self.normal(u, v).ok());
+                mesh.uvs.push([u, v]);
+            }
+        }
+        let index = |i: usize, j: usize| i * vs.len() + j;
+        let mut sums = vec![vec![0.0; 3]; normals.len()];
+        for i in 0..us.len() - 1 {
+            for j in 0..vs.len() - 1 {
+                // the cross product of the diagonals still works with a collapsed edge
+                let corners = [
+                    index(i, j),
+                    index(i + 1, j),
+                    index(i + 1, j + 1),
+                    index(i, j + 1),
+                ];
+                let [p00, p10, p11, p01] = corners.map(|k| &mesh.positions[k]);
+                if let Some(n) = normalize(&cross(&sub(p11, p00), &sub(p01, p10))) {
+                    for &k in corners.iter().filter(|&&k| normals[k].is_none()) {
+                        sums[k] = add(&sums[k], &n);
+                    }
+                }
+                let [k00, k10, k11, k01] = corners;
+                for triangle in [[k00, k10, k11], [k00, k11, k01]] {
+                    let [a, b, c] = triangle.map(|k| &mesh.positions[k]);
+                    let (ab, ac) = (sub(b, a), sub(c, a));
+                    let area = norm(&cross(&ab, &ac));
+                    if area > 1e-12 * (dot(&ab, &ab) + dot(&ac, &ac)) {
+                        mesh.triangles.push(triangle);
+                    }
+                }
+            }
+        }
+        mesh.normals = normals
+            .into_iter()
+            .zip(&sums)
+            .map(|(normal, sum)| {
+                normal
+                    .or_else(|| normalize(sum))
+                    .ok_or("The normal is undefined at a vertex of the mesh")
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(mesh)
+    }
+}
+
+// The distance of `p` to the line segment from `a` to `b`
+fn distance_to_segment(p: &[f64], a: &[f64], b: &[f64]) -> f64 {
+    let ab = sub(b, a);
+    let ap = sub(p, a);
+    let length2 = dot(&ab, &ab);
+    if length2 == 0.0 {
+        return norm(&ap);
+    }
+    let s = (dot(&ap, &ab) / length2).clamp(0.0, 1.0);
+    norm(&sub(&ap, &scale(&ab, s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::distance_to_segment;
+    use crate::nurbs::{NURBSCurve, NURBSSurface};
+    use crate::vector::{dot, norm, sub};
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+    // a quarter of a cylinder with radius 1 and height 2 around the z axis
+    fn quarter_cylinder() -> NURBSSurface {
+        let w = FRAC_1_SQRT_2;
+        let arc = [[1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        NURBSSurface::new(
+            2,
+            1,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0],
+            vec![vec![1.0, 1.0], vec![w, w], vec![1.0, 1.0]],
+            arc.iter()
+                .map(|p| vec![vec![p[0], p[1], 0.0], vec![p[0], p[1], 2.0]])
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_tessellate_curve() {
+        // a quarter circle followed by a straight line, joined with a kink at t = 1
+        let w = FRAC_1_SQRT_2;
+        let curve = NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 2.0],
+            vec![1.0, w, 1.0, 1.0, 1.0],
+            vec![
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+                vec![0.0, 1.0],
+                vec![-1.0, 2.0],
+                vec![-2.0, 3.0],
+            ],
+        )
+        .unwrap();
+        let coarse = curve.tessellate(1e-2).unwrap();
+        let fine = curve.tessellate(1e-5).unwrap();
+        assert!(fine.len() > coarse.len());
+        for polyline in [&coarse, &fine] {
+            // the vertex at the kink is kept and the line needs no vertices in between
+            assert!(polyline
+                .iter()
+                .any(|(t, p)| *t == 1.0 && p == &vec![0.0, 1.0]));
+            assert_eq!(polyline[polyline.len() - 2].0, 1.0);
+            assert_eq!(polyline[polyline.len() - 1], (2.0, vec![-2.0, 3.0]));
+        }
+        // the midpoints of the chords on the arc deviate at most by the tolerance
+        for pair in fine.windows(2).filter(|pair| pair[1].0 <= 1.0) {
+            let mid: Vec<f64> = pair[0]
+                .1
+                .iter()
+                .zip(&pair[1].1)
+                .map(|(a, b)| 0.5 * (a + b))
+                .collect();
+            assert!(1.0 - norm(&mid) <= 1e-5);
+            assert!(pair[0].0 < pair[1].0);
+        }
+        assert!(curve.tessellate(-1.0).is_err());
+        // the arc is not flat within 1e-16 on the shortest intervals
+        assert!(curve.tessellate(1e-16).is_err());
+        let zero_weight = NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![1.0, 0.0, 1.0],
+            vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 1.0]],
+        )
+        .unwrap();
+        assert!(zero_weight.tessellate(1e-2).is_err());
+    }
+
+    #[test]
+    fn test_tessellate_s_shaped() {
+        // a single cubic span that crosses its chord in the middle
+        let curve = NURBSCurve::new(
+            3,
+            vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0; 4],
+            vec![
+                vec![0.0, 0.0],
+                vec![1.0, 1.0],
+                vec![2.0, -1.0],
+                vec![3.0, 0.0],
+            ],
+        )
+        .unwrap();
+        for tolerance in [1e-1, 1e-3] {
+            let polyline = curve.tessellate(tolerance).unwrap();
+            assert!(polyline.len() > 3);
+            for pair in polyline.windows(2) {
+                let ((t0, a), (t1, b)) = (&pair[0], &pair[1]);
+                for i in 0..=50 {
+                    let p = curve.eval(t0 + (t1 - t0) * i as f64 / 50.0).unwrap();
+                    assert!(distance_to_segment(&p, a, b) <= tolerance);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tessellate_uniform() {
+        let mesh = quarter_cylinder().tessellate_uniform(5, 3).unwrap();
+        assert_eq!(mesh.positions.len(), 15);
+        assert_eq!(mesh.normals.len(), 15);
+        assert_eq!(mesh.uvs[7], [0.5, 0.5]);
+        assert_eq!(mesh.triangles.len(), 2 * 4 * 2);
+        // the normals agree with the winding of the triangles
+        for triangle in &mesh.triangles {
+            let face = mesh.face_normal(triangle);
+            assert!(dot(&face, &mesh.normals[triangle[0]]) > 0.9);
+        }
+    }
+
+    #[test]
+    fn test_tessellate_apex() {
+        // the profile starts with a double control point on the axis, so the revolved
+        // surface has no normal, not even a limit, at its apex
+        let profile = NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            vec![1.0; 3],
+            vec![
+                vec![0.0, 0.0, 1.0],
+                vec![0.0, 0.0, 1.0],
+                vec![1.0, 0.0, 0.0],
+            ],
+        )
+        .unwrap();
+        let cone = NURBSSurface::revolve(&profile, &[0.0; 3], &[0.0, 0.0, 1.0], 2.0 * PI).unwrap();
+        assert!(cone.normal(0.0, 0.5).is_err());
+        let mesh = cone.tessellate_uniform(3, 9).unwrap();
+        assert_eq!(mesh.normals.len(), mesh.positions.len());
+        for triangle in &mesh.triangles {
+            let face = mesh.face_normal(triangle);
+            for &k in triangle {
+                assert!((norm(&mesh.normals[k]) - 1.0).abs() < 1e-12);
+                assert!(dot(&face, &mesh.normals[k]) > 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tessellate_unresolved() {
+        // the profile turns by 90 degrees within a knot span too short to be split
+        let profile = NURBSCurve::new(
+            2,
+            vec![0.0, 0.0, 0.0, 1e-7, 1.0, 1.0, 1.0],
+            vec![1.0; 4],
+            vec![
+                vec![0.0, 0.0, 0.0],
+                vec![1.0, 0.0, 0.0],
+                vec![1.0, 1.0, 0.0],
+                vec![0.0, 1.0, 0.0],
+            ],
+        )
+        .unwrap();
+        let surface = NURBSSurface::extrude(&profile, &[0.0, 0.0, 1.0]).unwrap();
+        assert_eq!(
+            surface.tessellate(1e-3, 1.0),
+            Err("The surface cannot be tessellated within the tolerance")
+        );
+        assert!(surface.tessellate_uniform(4, 2).is_ok());
+    }
+
+    #[test]
+    fn test_tessellate_adaptive() {
+        let cylinder = quarter_cylinder();
+        for &tolerance in &[1e-2, 1e-4] {
+            let mesh = cylinder.tessellate(tolerance, 1.0).unwrap();
+            // the straight direction needs no refinement
+            assert!(mesh.uvs.iter().all(|uv| uv[1] == 0.0 || uv[1] == 1.0));
+            // the centroids of all triangles are close to the cylinder
+            for triangle in &mesh.triangles {
+                let mut centroid = [0.0; 3];
+                for &i in triangle {
+                    for (c, p) in centroid.iter_mut().zip(&mesh.positions[i]) {
+                        *c += p / 3.0;
+                    }
+                }
+                let radius = centroid[0].hypot(centroid[1]);
+                assert!((radius - 1.0).abs() < 2.0 * tolerance);
+            }
+        }
+        // a small maximum angle refines further than the tolerance needs
+        let coarse = cylinder.tessellate(1e-1, 1.0).unwrap();
+        let fine = cylinder.tessellate(1e-1, 0.05).unwrap();
+        assert!(fine.positions.len() > coarse.positions.len());
+        for i in 0..fine.positions.len() / 2 - 1 {
+            let n0 = &fine.normals[2 * i];
+            let n1 = &fine.normals[2 * i + 2];
+            assert!(norm(&sub(n0, n1)) <= 0.05);
+        }
+        assert!(cylinder.tessellate(0.0, 1.0).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+// vector.rs
+// Small helpers for the Vec<f64> points and vectors used throughout the crate.
+// Points can be of arbitrary dimension, except for cross() which needs 3D.
+
+pub fn add(a: &[f64], b: &[f64]) -> Vec<f64> {
+    a.iter().zip(b).map(|(x, y)| x + y).collect()
+}
+
+pub fn sub(a: &[f64], b: &[f64]) -> Vec<f64> {
+    a.iter().zip(b).map(|(x, y)| x - y).collect()
+}
+
+pub fn scale(a: &[f64], s: f64) -> Vec<f64> {
+    a.iter().map(|x| x * s).collect()
+}
+
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub fn norm(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+// Returns None for vectors too short to be normalized
+pub fn normalize(a: &[f64]) -> Option<Vec<f64>> {
+    let length = norm(a);
+    if length < f64::EPSILON {
+        None
+    } else {
+        Some(scale(a, 1.0 / length))
+    }
+}
+
+pub fn cross(a: &[f64], b: &[f64]) -> Vec<f64> {
+    vec![
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+// Lifts weighted points to homogeneous coordinates (w * P, w)
+pub fn homogeneous(points: &[Vec<f64>], weights: &[f64]) -> Vec<Vec<f64>> {
+    points
+        .iter()
+        .zip(weights)
+        .map(|(p, &w)| {
+            let mut pw = scale(p, w);
+            pw.push(w);
+            pw
+        })
+        .collect()
+}
+
+// Projects homogeneous points back to points and weights, points with zero weight
+// are kept as they are
+pub fn cartesian(points_w: &[Vec<f64>]) -> (Vec<Vec<f64>>, Vec<f64>) {
+    points_w
+        .iter()
+        .map(|pw| {
+            let (&w, p) = pw.split_last().unwrap();
+            if w == 0.0 {
+                (p.to_vec(), w)
+            } else {
+                (scale(p, 1.0 / w), w)
+            }
+        })
+        .unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_ops() {
+        let a = vec![1.0, 0.0, 0.0];
+        let b = vec![0.0, 2.0, 0.0];
+        assert_eq!(cross(&a, &b), vec![0.0, 0.0, 2.0]);
+        assert_eq!(dot(&a, &b), 0.0);
+        assert_eq!(norm(&sub(&a, &b)), 5.0_f64.sqrt());
+        assert_eq!(normalize(&b), Some(vec![0.0, 1.0, 0.0]));
+        assert_eq!(normalize(&[0.0, 0.0]), None);
+        assert_eq!(sub(&a, &scale(&b, 0.5)), vec![1.0, -1.0, 0.0]);
+        let points = vec![a.clone(), b.clone()];
+        let points_w = homogeneous(&points, &[2.0, 0.5]);
+        assert_eq!(points_w[1], vec![0.0, 1.0, 0.0, 0.5]);
+        assert_eq!(cartesian(&points_w), (points, vec![2.0, 0.5]));
+    }
+}